@@ -4,7 +4,7 @@ use clap_complete::{generate_to, shells::*};
 include!("src/cli.rs");
 
 fn main() {
-    let mut app = CLI::into_app();
+    let mut app = Cli::into_app();
     let binname = "tasktrack";
     app.set_bin_name(binname);
     let outdir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("completions/");
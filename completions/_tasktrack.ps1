@@ -21,23 +21,41 @@ Register-ArgumentCompleter -Native -CommandName 'tasktrack' -ScriptBlock {
 
     $completions = @(switch ($command) {
         'tasktrack' {
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
             [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
             [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
             [CompletionResult]::new('current', 'current', [CompletionResultType]::ParameterValue, 'Show current active task')
             [CompletionResult]::new('list', 'list', [CompletionResultType]::ParameterValue, 'List tasks')
             [CompletionResult]::new('new', 'new', [CompletionResultType]::ParameterValue, 'Create new task')
             [CompletionResult]::new('activate', 'activate', [CompletionResultType]::ParameterValue, 'Activate task')
+            [CompletionResult]::new('close', 'close', [CompletionResultType]::ParameterValue, 'Mark task as done')
+            [CompletionResult]::new('archive', 'archive', [CompletionResultType]::ParameterValue, 'Archive task, archived tasks are hidden from list')
+            [CompletionResult]::new('reopen', 'reopen', [CompletionResultType]::ParameterValue, 'Reopen done or archived task')
             [CompletionResult]::new('edit', 'edit', [CompletionResultType]::ParameterValue, 'Edit task description')
+            [CompletionResult]::new('delete', 'delete', [CompletionResultType]::ParameterValue, 'Delete task')
+            [CompletionResult]::new('rename', 'rename', [CompletionResultType]::ParameterValue, 'Rename task')
+            [CompletionResult]::new('merge', 'merge', [CompletionResultType]::ParameterValue, 'Merge source task into target task')
             [CompletionResult]::new('report', 'report', [CompletionResultType]::ParameterValue, 'Generate report')
+            [CompletionResult]::new('balance', 'balance', [CompletionResultType]::ParameterValue, 'Show overtime and flex-time balance')
             [CompletionResult]::new('show', 'show', [CompletionResultType]::ParameterValue, 'Show task description')
+            [CompletionResult]::new('note', 'note', [CompletionResultType]::ParameterValue, 'Add note to task, to current task if only text is given')
             [CompletionResult]::new('add-range', 'add-range', [CompletionResultType]::ParameterValue, 'Manulay add task time range')
             [CompletionResult]::new('vacation-add', 'vacation-add', [CompletionResultType]::ParameterValue, 'Add vacation')
             [CompletionResult]::new('vacation-remove', 'vacation-remove', [CompletionResultType]::ParameterValue, 'Remove vacation')
             [CompletionResult]::new('vacation-list', 'vacation-list', [CompletionResultType]::ParameterValue, 'List vacations')
+            [CompletionResult]::new('vacation-entitlement', 'vacation-entitlement', [CompletionResultType]::ParameterValue, 'Set vacation days entitlement of the year')
+            [CompletionResult]::new('vacation-balance', 'vacation-balance', [CompletionResultType]::ParameterValue, 'Show used and remaining vacation days of the year')
+            [CompletionResult]::new('absence', 'absence', [CompletionResultType]::ParameterValue, 'Manage absences of any kind')
+            [CompletionResult]::new('workpackage-budget', 'workpackage-budget', [CompletionResultType]::ParameterValue, 'Show or set workpackage budget')
+            [CompletionResult]::new('config', 'config', [CompletionResultType]::ParameterValue, 'Show or change settings')
+            [CompletionResult]::new('doctor', 'doctor', [CompletionResultType]::ParameterValue, 'Check time ranges consistency')
+            [CompletionResult]::new('undo', 'undo', [CompletionResultType]::ParameterValue, 'Undo last operation')
+            [CompletionResult]::new('redo', 'redo', [CompletionResultType]::ParameterValue, 'Redo last undone operation')
             [CompletionResult]::new('help', 'help', [CompletionResultType]::ParameterValue, 'Print this message or the help of the given subcommand(s)')
             break
         }
         'tasktrack;current' {
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
             [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
             [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
             break
@@ -45,6 +63,9 @@ Register-ArgumentCompleter -Native -CommandName 'tasktrack' -ScriptBlock {
         'tasktrack;list' {
             [CompletionResult]::new('-n', 'n', [CompletionResultType]::ParameterName, 'If set first *num_tasks*')
             [CompletionResult]::new('--num-tasks', 'num-tasks', [CompletionResultType]::ParameterName, 'If set first *num_tasks*')
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            [CompletionResult]::new('-a', 'a', [CompletionResultType]::ParameterName, 'Include archived tasks')
+            [CompletionResult]::new('--all', 'all', [CompletionResultType]::ParameterName, 'Include archived tasks')
             [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
             [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
             break
@@ -58,11 +79,33 @@ Register-ArgumentCompleter -Native -CommandName 'tasktrack' -ScriptBlock {
             [CompletionResult]::new('--workpackage', 'workpackage', [CompletionResultType]::ParameterName, 'Workpackage')
             [CompletionResult]::new('-o', 'o', [CompletionResultType]::ParameterName, 'Objective')
             [CompletionResult]::new('--objective', 'objective', [CompletionResultType]::ParameterName, 'Objective')
+            [CompletionResult]::new('-b', 'b', [CompletionResultType]::ParameterName, 'Estimated hours')
+            [CompletionResult]::new('--budget', 'budget', [CompletionResultType]::ParameterName, 'Estimated hours')
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
             [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
             [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
             break
         }
         'tasktrack;activate' {
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
+            break
+        }
+        'tasktrack;close' {
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
+            break
+        }
+        'tasktrack;archive' {
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
+            break
+        }
+        'tasktrack;reopen' {
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
             [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
             [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
             break
@@ -76,31 +119,97 @@ Register-ArgumentCompleter -Native -CommandName 'tasktrack' -ScriptBlock {
             [CompletionResult]::new('--workpackage', 'workpackage', [CompletionResultType]::ParameterName, 'Set workpackage to new value')
             [CompletionResult]::new('-o', 'o', [CompletionResultType]::ParameterName, 'Set objective to new value')
             [CompletionResult]::new('--objective', 'objective', [CompletionResultType]::ParameterName, 'Set objective to new value')
+            [CompletionResult]::new('-b', 'b', [CompletionResultType]::ParameterName, 'Set estimated hours to new value')
+            [CompletionResult]::new('--budget', 'budget', [CompletionResultType]::ParameterName, 'Set estimated hours to new value')
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
             [CompletionResult]::new('--drop-url', 'drop-url', [CompletionResultType]::ParameterName, 'Drop url value')
             [CompletionResult]::new('--drop-title', 'drop-title', [CompletionResultType]::ParameterName, 'Drop title value')
             [CompletionResult]::new('--drop-workpackage', 'drop-workpackage', [CompletionResultType]::ParameterName, 'Drop workpackage value')
             [CompletionResult]::new('--drop-objective', 'drop-objective', [CompletionResultType]::ParameterName, 'Drop objective value')
+            [CompletionResult]::new('--drop-budget', 'drop-budget', [CompletionResultType]::ParameterName, 'Drop estimated hours value')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
+            break
+        }
+        'tasktrack;delete' {
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            [CompletionResult]::new('--cascade', 'cascade', [CompletionResultType]::ParameterName, 'Delete task time ranges too')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
+            break
+        }
+        'tasktrack;rename' {
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
+            break
+        }
+        'tasktrack;merge' {
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
             [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
             [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
             break
         }
         'tasktrack;report' {
-            [CompletionResult]::new('-c', 'c', [CompletionResultType]::ParameterName, 'If set print report in csv format')
-            [CompletionResult]::new('--csv', 'csv', [CompletionResultType]::ParameterName, 'If set print report in csv format')
+            [CompletionResult]::new('-f', 'f', [CompletionResultType]::ParameterName, 'Output format of printed report')
+            [CompletionResult]::new('--format', 'format', [CompletionResultType]::ParameterName, 'Output format of printed report')
+            [CompletionResult]::new('--xlsx', 'xlsx', [CompletionResultType]::ParameterName, 'Write report with absences and vacations to Excel workbook instead of printing it. OpenDocument (ODS) export is not supported')
+            [CompletionResult]::new('--template', 'template', [CompletionResultType]::ParameterName, 'Render report through Tera template file or built-in template: timesheet.txt, timesheet.md, timesheet.html')
+            [CompletionResult]::new('--hours', 'hours', [CompletionResultType]::ParameterName, 'Hours to show: tracked wall-clock time, hours inside working calendar or both')
+            [CompletionResult]::new('--round', 'round', [CompletionResultType]::ParameterName, 'Add column with total hours rounded to increments of minutes, month columns stay exact. No rounding if 0')
+            [CompletionResult]::new('--round-mode', 'round-mode', [CompletionResultType]::ParameterName, 'Direction of rounding')
+            [CompletionResult]::new('--round-scope', 'round-scope', [CompletionResultType]::ParameterName, 'Round every range, day or total of a task')
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            [CompletionResult]::new('-c', 'c', [CompletionResultType]::ParameterName, 'If set print report in csv format, same as --format csv')
+            [CompletionResult]::new('--csv', 'csv', [CompletionResultType]::ParameterName, 'If set print report in csv format, same as --format csv')
+            [CompletionResult]::new('-n', 'n', [CompletionResultType]::ParameterName, 'Add column with task notes from the report period')
+            [CompletionResult]::new('--notes', 'notes', [CompletionResultType]::ParameterName, 'Add column with task notes from the report period')
+            [CompletionResult]::new('-b', 'b', [CompletionResultType]::ParameterName, 'Add task and workpackage budget columns')
+            [CompletionResult]::new('--budget', 'budget', [CompletionResultType]::ParameterName, 'Add task and workpackage budget columns')
+            [CompletionResult]::new('-a', 'a', [CompletionResultType]::ParameterName, 'Add table with absence days per month and kind')
+            [CompletionResult]::new('--absences', 'absences', [CompletionResultType]::ParameterName, 'Add table with absence days per month and kind')
+            [CompletionResult]::new('-w', 'w', [CompletionResultType]::ParameterName, 'Attribute hours to workpackage valid at the time of work')
+            [CompletionResult]::new('--wp-history', 'wp-history', [CompletionResultType]::ParameterName, 'Attribute hours to workpackage valid at the time of work')
+            [CompletionResult]::new('--no-totals', 'no-totals', [CompletionResultType]::ParameterName, 'Omit totals rows, e.g. for further processing of csv output. Workbook written by --xlsx gets no SUM formulas then')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
+            break
+        }
+        'tasktrack;balance' {
+            [CompletionResult]::new('-s', 's', [CompletionResultType]::ParameterName, 'Date since show balance. Format %d.%m.%Y')
+            [CompletionResult]::new('--since', 'since', [CompletionResultType]::ParameterName, 'Date since show balance. Format %d.%m.%Y')
+            [CompletionResult]::new('-t', 't', [CompletionResultType]::ParameterName, 'Date till show balance, today if not set. Format %d.%m.%Y')
+            [CompletionResult]::new('--till', 'till', [CompletionResultType]::ParameterName, 'Date till show balance, today if not set. Format %d.%m.%Y')
+            [CompletionResult]::new('-b', 'b', [CompletionResultType]::ParameterName, 'Balance period')
+            [CompletionResult]::new('--by', 'by', [CompletionResultType]::ParameterName, 'Balance period')
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
             [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
             [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
             break
         }
         'tasktrack;show' {
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            [CompletionResult]::new('--history', 'history', [CompletionResultType]::ParameterName, 'Show history of task changes')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
+            break
+        }
+        'tasktrack;note' {
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            [CompletionResult]::new('-a', 'a', [CompletionResultType]::ParameterName, 'Attach note to the active time range of the task')
+            [CompletionResult]::new('--attach', 'attach', [CompletionResultType]::ParameterName, 'Attach note to the active time range of the task')
             [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
             [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
             break
         }
         'tasktrack;add-range' {
-            [CompletionResult]::new('-s', 's', [CompletionResultType]::ParameterName, 'Date since generate report. Format %d-%m-%Y')
-            [CompletionResult]::new('--since', 'since', [CompletionResultType]::ParameterName, 'Date since generate report. Format %d-%m-%Y')
-            [CompletionResult]::new('-t', 't', [CompletionResultType]::ParameterName, 'Date till generate report. Format %d-%m-%Y')
-            [CompletionResult]::new('--till', 'till', [CompletionResultType]::ParameterName, 'Date till generate report. Format %d-%m-%Y')
+            [CompletionResult]::new('-s', 's', [CompletionResultType]::ParameterName, 'Start of the range at the beginning of the day. Format %d.%m.%Y')
+            [CompletionResult]::new('--since', 'since', [CompletionResultType]::ParameterName, 'Start of the range at the beginning of the day. Format %d.%m.%Y')
+            [CompletionResult]::new('-t', 't', [CompletionResultType]::ParameterName, 'End of the range at the end of the day, stops the open range without --since. Format %d.%m.%Y')
+            [CompletionResult]::new('--till', 'till', [CompletionResultType]::ParameterName, 'End of the range at the end of the day, stops the open range without --since. Format %d.%m.%Y')
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            [CompletionResult]::new('--split', 'split', [CompletionResultType]::ParameterName, 'Add only parts of the range not overlapping with other ranges')
+            [CompletionResult]::new('--replace', 'replace', [CompletionResultType]::ParameterName, 'Cut overlapping parts out of other ranges')
             [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
             [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
             break
@@ -110,6 +219,12 @@ Register-ArgumentCompleter -Native -CommandName 'tasktrack' -ScriptBlock {
             [CompletionResult]::new('--since', 'since', [CompletionResultType]::ParameterName, 'since')
             [CompletionResult]::new('-t', 't', [CompletionResultType]::ParameterName, 't')
             [CompletionResult]::new('--till', 'till', [CompletionResultType]::ParameterName, 'till')
+            [CompletionResult]::new('--half', 'half', [CompletionResultType]::ParameterName, 'Take only half of the day, since and till must be the same date')
+            [CompletionResult]::new('--from', 'from', [CompletionResultType]::ParameterName, 'Time vacation starts at on since date. Format %H:%M')
+            [CompletionResult]::new('--to', 'to', [CompletionResultType]::ParameterName, 'Time vacation ends at on till date. Format %H:%M')
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            [CompletionResult]::new('-m', 'm', [CompletionResultType]::ParameterName, 'Merge with overlapping vacations of the same kind')
+            [CompletionResult]::new('--merge', 'merge', [CompletionResultType]::ParameterName, 'Merge with overlapping vacations of the same kind')
             [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
             [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
             break
@@ -117,6 +232,7 @@ Register-ArgumentCompleter -Native -CommandName 'tasktrack' -ScriptBlock {
         'tasktrack;vacation-remove' {
             [CompletionResult]::new('-i', 'i', [CompletionResultType]::ParameterName, 'i')
             [CompletionResult]::new('--id', 'id', [CompletionResultType]::ParameterName, 'id')
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
             [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
             [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
             break
@@ -126,11 +242,103 @@ Register-ArgumentCompleter -Native -CommandName 'tasktrack' -ScriptBlock {
             [CompletionResult]::new('--since', 'since', [CompletionResultType]::ParameterName, 'since')
             [CompletionResult]::new('-t', 't', [CompletionResultType]::ParameterName, 't')
             [CompletionResult]::new('--till', 'till', [CompletionResultType]::ParameterName, 'till')
+            [CompletionResult]::new('-k', 'k', [CompletionResultType]::ParameterName, 'List only absences of the kind')
+            [CompletionResult]::new('--kind', 'kind', [CompletionResultType]::ParameterName, 'List only absences of the kind')
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
+            break
+        }
+        'tasktrack;vacation-entitlement' {
+            [CompletionResult]::new('-c', 'c', [CompletionResultType]::ParameterName, 'Days carried over from the previous year, remaining days of previous year if not set')
+            [CompletionResult]::new('--carry-over', 'carry-over', [CompletionResultType]::ParameterName, 'Days carried over from the previous year, remaining days of previous year if not set')
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
+            break
+        }
+        'tasktrack;vacation-balance' {
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
+            break
+        }
+        'tasktrack;absence' {
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
+            [CompletionResult]::new('add', 'add', [CompletionResultType]::ParameterValue, 'Add absence')
+            [CompletionResult]::new('summary', 'summary', [CompletionResultType]::ParameterValue, 'Show absence days per kind and remaining vacation days')
+            [CompletionResult]::new('help', 'help', [CompletionResultType]::ParameterValue, 'Print this message or the help of the given subcommand(s)')
+            break
+        }
+        'tasktrack;absence;add' {
+            [CompletionResult]::new('-s', 's', [CompletionResultType]::ParameterName, 's')
+            [CompletionResult]::new('--since', 'since', [CompletionResultType]::ParameterName, 'since')
+            [CompletionResult]::new('-t', 't', [CompletionResultType]::ParameterName, 't')
+            [CompletionResult]::new('--till', 'till', [CompletionResultType]::ParameterName, 'till')
+            [CompletionResult]::new('--half', 'half', [CompletionResultType]::ParameterName, 'Take only half of the day, since and till must be the same date')
+            [CompletionResult]::new('--from', 'from', [CompletionResultType]::ParameterName, 'Time vacation starts at on since date. Format %H:%M')
+            [CompletionResult]::new('--to', 'to', [CompletionResultType]::ParameterName, 'Time vacation ends at on till date. Format %H:%M')
+            [CompletionResult]::new('-k', 'k', [CompletionResultType]::ParameterName, 'Absence kind')
+            [CompletionResult]::new('--kind', 'kind', [CompletionResultType]::ParameterName, 'Absence kind')
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            [CompletionResult]::new('-m', 'm', [CompletionResultType]::ParameterName, 'Merge with overlapping vacations of the same kind')
+            [CompletionResult]::new('--merge', 'merge', [CompletionResultType]::ParameterName, 'Merge with overlapping vacations of the same kind')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
+            break
+        }
+        'tasktrack;absence;summary' {
+            [CompletionResult]::new('-y', 'y', [CompletionResultType]::ParameterName, 'Year, current year if not set')
+            [CompletionResult]::new('--year', 'year', [CompletionResultType]::ParameterName, 'Year, current year if not set')
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
+            break
+        }
+        'tasktrack;absence;help' {
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            break
+        }
+        'tasktrack;workpackage-budget' {
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            [CompletionResult]::new('--drop', 'drop', [CompletionResultType]::ParameterName, 'Drop budget value')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
+            break
+        }
+        'tasktrack;config' {
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            [CompletionResult]::new('--unset', 'unset', [CompletionResultType]::ParameterName, 'Reset setting to default value')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
+            break
+        }
+        'tasktrack;doctor' {
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            [CompletionResult]::new('-f', 'f', [CompletionResultType]::ParameterName, 'Apply suggested fixes')
+            [CompletionResult]::new('--fix', 'fix', [CompletionResultType]::ParameterName, 'Apply suggested fixes')
+            [CompletionResult]::new('-i', 'i', [CompletionResultType]::ParameterName, 'Ask before applying each fix')
+            [CompletionResult]::new('--interactive', 'interactive', [CompletionResultType]::ParameterName, 'Ask before applying each fix')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
+            break
+        }
+        'tasktrack;undo' {
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
+            [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
+            [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
+            break
+        }
+        'tasktrack;redo' {
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
             [CompletionResult]::new('-h', 'h', [CompletionResultType]::ParameterName, 'Print help information')
             [CompletionResult]::new('--help', 'help', [CompletionResultType]::ParameterName, 'Print help information')
             break
         }
         'tasktrack;help' {
+            [CompletionResult]::new('--now', 'now', [CompletionResultType]::ParameterName, 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted')
             break
         }
     })
@@ -1,45 +1,156 @@
+complete -c tasktrack -n "__fish_use_subcommand" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
 complete -c tasktrack -n "__fish_use_subcommand" -s h -l help -d 'Print help information'
 complete -c tasktrack -n "__fish_use_subcommand" -f -a "current" -d 'Show current active task'
 complete -c tasktrack -n "__fish_use_subcommand" -f -a "list" -d 'List tasks'
 complete -c tasktrack -n "__fish_use_subcommand" -f -a "new" -d 'Create new task'
 complete -c tasktrack -n "__fish_use_subcommand" -f -a "activate" -d 'Activate task'
+complete -c tasktrack -n "__fish_use_subcommand" -f -a "close" -d 'Mark task as done'
+complete -c tasktrack -n "__fish_use_subcommand" -f -a "archive" -d 'Archive task, archived tasks are hidden from list'
+complete -c tasktrack -n "__fish_use_subcommand" -f -a "reopen" -d 'Reopen done or archived task'
 complete -c tasktrack -n "__fish_use_subcommand" -f -a "edit" -d 'Edit task description'
+complete -c tasktrack -n "__fish_use_subcommand" -f -a "delete" -d 'Delete task'
+complete -c tasktrack -n "__fish_use_subcommand" -f -a "rename" -d 'Rename task'
+complete -c tasktrack -n "__fish_use_subcommand" -f -a "merge" -d 'Merge source task into target task'
 complete -c tasktrack -n "__fish_use_subcommand" -f -a "report" -d 'Generate report'
+complete -c tasktrack -n "__fish_use_subcommand" -f -a "balance" -d 'Show overtime and flex-time balance'
 complete -c tasktrack -n "__fish_use_subcommand" -f -a "show" -d 'Show task description'
+complete -c tasktrack -n "__fish_use_subcommand" -f -a "note" -d 'Add note to task, to current task if only text is given'
 complete -c tasktrack -n "__fish_use_subcommand" -f -a "add-range" -d 'Manulay add task time range'
 complete -c tasktrack -n "__fish_use_subcommand" -f -a "vacation-add" -d 'Add vacation'
 complete -c tasktrack -n "__fish_use_subcommand" -f -a "vacation-remove" -d 'Remove vacation'
 complete -c tasktrack -n "__fish_use_subcommand" -f -a "vacation-list" -d 'List vacations'
+complete -c tasktrack -n "__fish_use_subcommand" -f -a "vacation-entitlement" -d 'Set vacation days entitlement of the year'
+complete -c tasktrack -n "__fish_use_subcommand" -f -a "vacation-balance" -d 'Show used and remaining vacation days of the year'
+complete -c tasktrack -n "__fish_use_subcommand" -f -a "absence" -d 'Manage absences of any kind'
+complete -c tasktrack -n "__fish_use_subcommand" -f -a "workpackage-budget" -d 'Show or set workpackage budget'
+complete -c tasktrack -n "__fish_use_subcommand" -f -a "config" -d 'Show or change settings'
+complete -c tasktrack -n "__fish_use_subcommand" -f -a "doctor" -d 'Check time ranges consistency'
+complete -c tasktrack -n "__fish_use_subcommand" -f -a "undo" -d 'Undo last operation'
+complete -c tasktrack -n "__fish_use_subcommand" -f -a "redo" -d 'Redo last undone operation'
 complete -c tasktrack -n "__fish_use_subcommand" -f -a "help" -d 'Print this message or the help of the given subcommand(s)'
+complete -c tasktrack -n "__fish_seen_subcommand_from current" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
 complete -c tasktrack -n "__fish_seen_subcommand_from current" -s h -l help -d 'Print help information'
 complete -c tasktrack -n "__fish_seen_subcommand_from list" -s n -l num-tasks -d 'If set first *num_tasks*' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from list" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from list" -s a -l all -d 'Include archived tasks'
 complete -c tasktrack -n "__fish_seen_subcommand_from list" -s h -l help -d 'Print help information'
 complete -c tasktrack -n "__fish_seen_subcommand_from new" -s u -l url -d 'Jira issue url' -r
 complete -c tasktrack -n "__fish_seen_subcommand_from new" -s t -l title -d 'Some short text description' -r
 complete -c tasktrack -n "__fish_seen_subcommand_from new" -s w -l workpackage -d 'Workpackage' -r
 complete -c tasktrack -n "__fish_seen_subcommand_from new" -s o -l objective -d 'Objective' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from new" -s b -l budget -d 'Estimated hours' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from new" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
 complete -c tasktrack -n "__fish_seen_subcommand_from new" -s h -l help -d 'Print help information'
+complete -c tasktrack -n "__fish_seen_subcommand_from activate" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
 complete -c tasktrack -n "__fish_seen_subcommand_from activate" -s h -l help -d 'Print help information'
+complete -c tasktrack -n "__fish_seen_subcommand_from close" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from close" -s h -l help -d 'Print help information'
+complete -c tasktrack -n "__fish_seen_subcommand_from archive" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from archive" -s h -l help -d 'Print help information'
+complete -c tasktrack -n "__fish_seen_subcommand_from reopen" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from reopen" -s h -l help -d 'Print help information'
 complete -c tasktrack -n "__fish_seen_subcommand_from edit" -s u -l url -d 'Set url to new value' -r
 complete -c tasktrack -n "__fish_seen_subcommand_from edit" -s t -l title -d 'Set title to new value' -r
 complete -c tasktrack -n "__fish_seen_subcommand_from edit" -s w -l workpackage -d 'Set workpackage to new value' -r
 complete -c tasktrack -n "__fish_seen_subcommand_from edit" -s o -l objective -d 'Set objective to new value' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from edit" -s b -l budget -d 'Set estimated hours to new value' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from edit" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
 complete -c tasktrack -n "__fish_seen_subcommand_from edit" -l drop-url -d 'Drop url value'
 complete -c tasktrack -n "__fish_seen_subcommand_from edit" -l drop-title -d 'Drop title value'
 complete -c tasktrack -n "__fish_seen_subcommand_from edit" -l drop-workpackage -d 'Drop workpackage value'
 complete -c tasktrack -n "__fish_seen_subcommand_from edit" -l drop-objective -d 'Drop objective value'
+complete -c tasktrack -n "__fish_seen_subcommand_from edit" -l drop-budget -d 'Drop estimated hours value'
 complete -c tasktrack -n "__fish_seen_subcommand_from edit" -s h -l help -d 'Print help information'
-complete -c tasktrack -n "__fish_seen_subcommand_from report" -s c -l csv -d 'If set print report in csv format'
+complete -c tasktrack -n "__fish_seen_subcommand_from delete" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from delete" -l cascade -d 'Delete task time ranges too'
+complete -c tasktrack -n "__fish_seen_subcommand_from delete" -s h -l help -d 'Print help information'
+complete -c tasktrack -n "__fish_seen_subcommand_from rename" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from rename" -s h -l help -d 'Print help information'
+complete -c tasktrack -n "__fish_seen_subcommand_from merge" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from merge" -s h -l help -d 'Print help information'
+complete -c tasktrack -n "__fish_seen_subcommand_from report" -s f -l format -d 'Output format of printed report' -r -f -a "{table	,csv	,json	,markdown	,html	}"
+complete -c tasktrack -n "__fish_seen_subcommand_from report" -l xlsx -d 'Write report with absences and vacations to Excel workbook instead of printing it. OpenDocument (ODS) export is not supported' -r -F
+complete -c tasktrack -n "__fish_seen_subcommand_from report" -l template -d 'Render report through Tera template file or built-in template: timesheet.txt, timesheet.md, timesheet.html' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from report" -l hours -d 'Hours to show: tracked wall-clock time, hours inside working calendar or both' -r -f -a "{raw	,normalized	,both	}"
+complete -c tasktrack -n "__fish_seen_subcommand_from report" -l round -d 'Add column with total hours rounded to increments of minutes, month columns stay exact. No rounding if 0' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from report" -l round-mode -d 'Direction of rounding' -r -f -a "{up	,down	,nearest	}"
+complete -c tasktrack -n "__fish_seen_subcommand_from report" -l round-scope -d 'Round every range, day or total of a task' -r -f -a "{range	,day	,task	}"
+complete -c tasktrack -n "__fish_seen_subcommand_from report" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from report" -s c -l csv -d 'If set print report in csv format, same as --format csv'
+complete -c tasktrack -n "__fish_seen_subcommand_from report" -s n -l notes -d 'Add column with task notes from the report period'
+complete -c tasktrack -n "__fish_seen_subcommand_from report" -s b -l budget -d 'Add task and workpackage budget columns'
+complete -c tasktrack -n "__fish_seen_subcommand_from report" -s a -l absences -d 'Add table with absence days per month and kind'
+complete -c tasktrack -n "__fish_seen_subcommand_from report" -s w -l wp-history -d 'Attribute hours to workpackage valid at the time of work'
+complete -c tasktrack -n "__fish_seen_subcommand_from report" -l no-totals -d 'Omit totals rows, e.g. for further processing of csv output. Workbook written by --xlsx gets no SUM formulas then'
 complete -c tasktrack -n "__fish_seen_subcommand_from report" -s h -l help -d 'Print help information'
+complete -c tasktrack -n "__fish_seen_subcommand_from balance" -s s -l since -d 'Date since show balance. Format %d.%m.%Y' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from balance" -s t -l till -d 'Date till show balance, today if not set. Format %d.%m.%Y' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from balance" -s b -l by -d 'Balance period' -r -f -a "{day	,week	,month	}"
+complete -c tasktrack -n "__fish_seen_subcommand_from balance" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from balance" -s h -l help -d 'Print help information'
+complete -c tasktrack -n "__fish_seen_subcommand_from show" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from show" -l history -d 'Show history of task changes'
 complete -c tasktrack -n "__fish_seen_subcommand_from show" -s h -l help -d 'Print help information'
-complete -c tasktrack -n "__fish_seen_subcommand_from add-range" -s s -l since -d 'Date since generate report. Format %d-%m-%Y' -r
-complete -c tasktrack -n "__fish_seen_subcommand_from add-range" -s t -l till -d 'Date till generate report. Format %d-%m-%Y' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from note" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from note" -s a -l attach -d 'Attach note to the active time range of the task'
+complete -c tasktrack -n "__fish_seen_subcommand_from note" -s h -l help -d 'Print help information'
+complete -c tasktrack -n "__fish_seen_subcommand_from add-range" -s s -l since -d 'Start of the range at the beginning of the day. Format %d.%m.%Y' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from add-range" -s t -l till -d 'End of the range at the end of the day, stops the open range without --since. Format %d.%m.%Y' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from add-range" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from add-range" -l split -d 'Add only parts of the range not overlapping with other ranges'
+complete -c tasktrack -n "__fish_seen_subcommand_from add-range" -l replace -d 'Cut overlapping parts out of other ranges'
 complete -c tasktrack -n "__fish_seen_subcommand_from add-range" -s h -l help -d 'Print help information'
 complete -c tasktrack -n "__fish_seen_subcommand_from vacation-add" -s s -l since -r
 complete -c tasktrack -n "__fish_seen_subcommand_from vacation-add" -s t -l till -r
+complete -c tasktrack -n "__fish_seen_subcommand_from vacation-add" -l half -d 'Take only half of the day, since and till must be the same date' -r -f -a "{morning	,afternoon	}"
+complete -c tasktrack -n "__fish_seen_subcommand_from vacation-add" -l from -d 'Time vacation starts at on since date. Format %H:%M' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from vacation-add" -l to -d 'Time vacation ends at on till date. Format %H:%M' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from vacation-add" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from vacation-add" -s m -l merge -d 'Merge with overlapping vacations of the same kind'
 complete -c tasktrack -n "__fish_seen_subcommand_from vacation-add" -s h -l help -d 'Print help information'
 complete -c tasktrack -n "__fish_seen_subcommand_from vacation-remove" -s i -l id -r
+complete -c tasktrack -n "__fish_seen_subcommand_from vacation-remove" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
 complete -c tasktrack -n "__fish_seen_subcommand_from vacation-remove" -s h -l help -d 'Print help information'
 complete -c tasktrack -n "__fish_seen_subcommand_from vacation-list" -s s -l since -r
 complete -c tasktrack -n "__fish_seen_subcommand_from vacation-list" -s t -l till -r
+complete -c tasktrack -n "__fish_seen_subcommand_from vacation-list" -s k -l kind -d 'List only absences of the kind' -r -f -a "{vacation	,sick	,training	,parental	,other	}"
+complete -c tasktrack -n "__fish_seen_subcommand_from vacation-list" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
 complete -c tasktrack -n "__fish_seen_subcommand_from vacation-list" -s h -l help -d 'Print help information'
+complete -c tasktrack -n "__fish_seen_subcommand_from vacation-entitlement" -s c -l carry-over -d 'Days carried over from the previous year, remaining days of previous year if not set' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from vacation-entitlement" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from vacation-entitlement" -s h -l help -d 'Print help information'
+complete -c tasktrack -n "__fish_seen_subcommand_from vacation-balance" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from vacation-balance" -s h -l help -d 'Print help information'
+complete -c tasktrack -n "__fish_seen_subcommand_from absence; and not __fish_seen_subcommand_from add; and not __fish_seen_subcommand_from summary; and not __fish_seen_subcommand_from help" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from absence; and not __fish_seen_subcommand_from add; and not __fish_seen_subcommand_from summary; and not __fish_seen_subcommand_from help" -s h -l help -d 'Print help information'
+complete -c tasktrack -n "__fish_seen_subcommand_from absence; and not __fish_seen_subcommand_from add; and not __fish_seen_subcommand_from summary; and not __fish_seen_subcommand_from help" -f -a "add" -d 'Add absence'
+complete -c tasktrack -n "__fish_seen_subcommand_from absence; and not __fish_seen_subcommand_from add; and not __fish_seen_subcommand_from summary; and not __fish_seen_subcommand_from help" -f -a "summary" -d 'Show absence days per kind and remaining vacation days'
+complete -c tasktrack -n "__fish_seen_subcommand_from absence; and not __fish_seen_subcommand_from add; and not __fish_seen_subcommand_from summary; and not __fish_seen_subcommand_from help" -f -a "help" -d 'Print this message or the help of the given subcommand(s)'
+complete -c tasktrack -n "__fish_seen_subcommand_from absence; and __fish_seen_subcommand_from add" -s s -l since -r
+complete -c tasktrack -n "__fish_seen_subcommand_from absence; and __fish_seen_subcommand_from add" -s t -l till -r
+complete -c tasktrack -n "__fish_seen_subcommand_from absence; and __fish_seen_subcommand_from add" -l half -d 'Take only half of the day, since and till must be the same date' -r -f -a "{morning	,afternoon	}"
+complete -c tasktrack -n "__fish_seen_subcommand_from absence; and __fish_seen_subcommand_from add" -l from -d 'Time vacation starts at on since date. Format %H:%M' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from absence; and __fish_seen_subcommand_from add" -l to -d 'Time vacation ends at on till date. Format %H:%M' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from absence; and __fish_seen_subcommand_from add" -s k -l kind -d 'Absence kind' -r -f -a "{vacation	,sick	,training	,parental	,other	}"
+complete -c tasktrack -n "__fish_seen_subcommand_from absence; and __fish_seen_subcommand_from add" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from absence; and __fish_seen_subcommand_from add" -s m -l merge -d 'Merge with overlapping vacations of the same kind'
+complete -c tasktrack -n "__fish_seen_subcommand_from absence; and __fish_seen_subcommand_from add" -s h -l help -d 'Print help information'
+complete -c tasktrack -n "__fish_seen_subcommand_from absence; and __fish_seen_subcommand_from summary" -s y -l year -d 'Year, current year if not set' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from absence; and __fish_seen_subcommand_from summary" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from absence; and __fish_seen_subcommand_from summary" -s h -l help -d 'Print help information'
+complete -c tasktrack -n "__fish_seen_subcommand_from absence; and __fish_seen_subcommand_from help" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from workpackage-budget" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from workpackage-budget" -l drop -d 'Drop budget value'
+complete -c tasktrack -n "__fish_seen_subcommand_from workpackage-budget" -s h -l help -d 'Print help information'
+complete -c tasktrack -n "__fish_seen_subcommand_from config" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from config" -l unset -d 'Reset setting to default value'
+complete -c tasktrack -n "__fish_seen_subcommand_from config" -s h -l help -d 'Print help information'
+complete -c tasktrack -n "__fish_seen_subcommand_from doctor" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from doctor" -s f -l fix -d 'Apply suggested fixes'
+complete -c tasktrack -n "__fish_seen_subcommand_from doctor" -s i -l interactive -d 'Ask before applying each fix'
+complete -c tasktrack -n "__fish_seen_subcommand_from doctor" -s h -l help -d 'Print help information'
+complete -c tasktrack -n "__fish_seen_subcommand_from undo" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from undo" -s h -l help -d 'Print help information'
+complete -c tasktrack -n "__fish_seen_subcommand_from redo" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
+complete -c tasktrack -n "__fish_seen_subcommand_from redo" -s h -l help -d 'Print help information'
+complete -c tasktrack -n "__fish_seen_subcommand_from help" -l now -d 'Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted' -r
@@ -0,0 +1,253 @@
+//! Flex-time balance of tracked against expected working time and vacation balance.
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Duration, NaiveDate};
+
+use crate::database::AbsenceKind;
+use crate::error::Result;
+use crate::report;
+use crate::settings;
+use crate::storage::Storage;
+use crate::time_ranges::{self, DateTime};
+
+/// Length of periods flex-time balance is grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BalancePeriod {
+    /// Every day.
+    Day,
+    /// ISO week.
+    Week,
+    /// Calendar month.
+    Month,
+}
+
+/// Tracked and expected working time of one period.
+#[derive(Debug, Clone)]
+pub struct PeriodBalance {
+    /// Day, ISO week or month of the period.
+    pub name: String,
+    /// Time tracked in the period.
+    pub tracked: Duration,
+    /// Working time expected by the calendar.
+    pub expected: Duration,
+    /// Balance at the end of the period counted from the balance start.
+    pub balance: Duration,
+}
+
+impl PeriodBalance {
+    /// Time tracked over the expected one, negative if less was tracked.
+    pub fn overtime(&self) -> Duration {
+        self.tracked - self.expected
+    }
+}
+
+/// Flex-time balance with periods shown to the user.
+#[derive(Debug, Clone)]
+pub struct FlexTimeBalance {
+    /// First day the balance is counted from.
+    pub start: NaiveDate,
+    /// Periods since the first listed day.
+    pub periods: Vec<PeriodBalance>,
+    /// Balance at the end of the last period.
+    pub balance: Duration,
+}
+
+/// Counts balance from the `balance-start` setting or the first tracked day till `till`,
+/// now at most. Periods before `since` count into the balance without being listed.
+/// Returns None if balance start is not set and nothing is tracked yet.
+pub fn flex_time_balance(
+    db: &impl Storage,
+    since: Option<NaiveDate>,
+    till: Option<DateTime>,
+    by: BalancePeriod,
+) -> Result<Option<FlexTimeBalance>> {
+    let start = match settings::parse_date(&settings::get(db, settings::BALANCE_START)?) {
+        Some(date) => date,
+        None => match db.get_first_range_time()? {
+            Some(dt) => dt.date_naive(),
+            None => return Ok(None),
+        },
+    };
+    let now = db.clock().now();
+    let till = till.unwrap_or(now).min(now);
+    let start_dt = chrono::DateTime::from_utc(start.and_hms(0, 0, 0), chrono::Utc);
+
+    let mut tracked = BTreeMap::new();
+    for ranges in db
+        .select_time_ranges(None, Some(start_dt), Some(till))?
+        .values()
+    {
+        for (day, duration) in time_ranges::daily_durations(ranges, start_dt, till) {
+            let d = tracked.entry(day).or_insert_with(Duration::zero);
+            *d = *d + duration;
+        }
+    }
+
+    let calendar = db.work_calendar()?;
+    let since = since.unwrap_or(start);
+    let period_name = |day: NaiveDate| match by {
+        BalancePeriod::Day => day.format("%d.%m.%Y").to_string(),
+        BalancePeriod::Week => {
+            let week = day.iso_week();
+            format!("{}-W{:02}", week.year(), week.week())
+        }
+        BalancePeriod::Month => day.format("%m.%Y").to_string(),
+    };
+
+    let mut periods: Vec<PeriodBalance> = Vec::new();
+    let mut balance = Duration::zero();
+    let mut day = start;
+    while day <= till.date_naive() {
+        let worked = tracked
+            .get(&day)
+            .map(|d| Duration::seconds(d.num_seconds()))
+            .unwrap_or_else(Duration::zero);
+        let expected = time_ranges::expected_work_time(day, &calendar);
+        balance = balance + worked - expected;
+        if day >= since {
+            let name = period_name(day);
+            match periods.last_mut() {
+                Some(last) if last.name == name => {
+                    last.tracked = last.tracked + worked;
+                    last.expected = last.expected + expected;
+                    last.balance = balance;
+                }
+                _ => periods.push(PeriodBalance {
+                    name,
+                    tracked: worked,
+                    expected,
+                    balance,
+                }),
+            }
+        }
+        day = day.succ();
+    }
+    Ok(Some(FlexTimeBalance {
+        start,
+        periods,
+        balance,
+    }))
+}
+
+/// Vacation days of a year.
+#[derive(Debug, Clone, Copy)]
+pub struct VacationBalance {
+    /// Vacation days of the year.
+    pub entitlement: f64,
+    /// Days carried over from the previous year.
+    pub carry_over: f64,
+    /// Vacation days taken in the year.
+    pub used: f64,
+}
+
+impl VacationBalance {
+    /// Days left, negative if more days were used.
+    pub fn remaining(&self) -> f64 {
+        self.entitlement + self.carry_over - self.used
+    }
+}
+
+/// Vacation balance of the year. Without entitlement of the year the `vacation-days`
+/// setting is used, carry-over is the remaining of the previous year limited by
+/// the `vacation-carry-over-max` setting if it is not set explicitly.
+pub fn vacation_balance(db: &impl Storage, year: i32) -> Result<VacationBalance> {
+    let entitlement = db.get_vacation_entitlement(year)?;
+    let carry_over = match entitlement {
+        Some((_, Some(carry_over))) => carry_over,
+        _ if db.get_vacation_entitlement(year - 1)?.is_some() => {
+            let remaining = vacation_balance(db, year - 1)?.remaining().max(0.0);
+            match settings::get(db, settings::VACATION_CARRY_OVER_MAX)?.parse::<f64>() {
+                Ok(max) => remaining.min(max),
+                Err(_) => remaining,
+            }
+        }
+        _ => 0.0,
+    };
+    let entitlement = match entitlement {
+        Some((days, _)) => days,
+        None => settings::get(db, settings::VACATION_DAYS)?
+            .parse()
+            .unwrap_or(0.0),
+    };
+    let used = report::absence_days_by_kind(
+        db,
+        NaiveDate::from_ymd(year, 1, 1),
+        NaiveDate::from_ymd(year, 12, 31),
+    )?[&AbsenceKind::Vacation];
+    Ok(VacationBalance {
+        entitlement,
+        carry_over,
+        used,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{flex_time_balance, vacation_balance, BalancePeriod};
+    use crate::database::{AbsenceKind, START_VALUE, STOP_VALUE};
+    use crate::settings;
+    use crate::storage::{MemoryStorage, Storage};
+    use crate::time_ranges::FixedClock;
+    use chrono::{Duration, TimeZone};
+
+    fn storage() -> MemoryStorage {
+        let dt = |d, h| chrono::Utc.ymd(2023, 3, d).and_hms(h, 0, 0);
+        let mut storage = MemoryStorage::with_clock(Box::new(FixedClock(dt(10, 18))));
+        storage.new_task("A", None, None, None, None, None).unwrap();
+        for (start, end) in [(dt(6, 9), dt(6, 17)), (dt(7, 9), dt(7, 19))] {
+            storage
+                .update_time_ranges("A", START_VALUE, Some(start))
+                .unwrap();
+            storage
+                .update_time_ranges("A", STOP_VALUE, Some(end))
+                .unwrap();
+        }
+        storage
+            .add_vacation(dt(9, 0), dt(9, 23), AbsenceKind::Vacation)
+            .unwrap();
+        storage
+    }
+
+    #[test]
+    fn flex_time_balance_test() {
+        let storage = storage();
+        let balance = flex_time_balance(&storage, None, None, BalancePeriod::Day)
+            .unwrap()
+            .unwrap();
+        assert_eq!(balance.start, chrono::NaiveDate::from_ymd(2023, 3, 6));
+        assert_eq!(balance.periods.len(), 5);
+        assert_eq!(balance.periods[1].overtime(), Duration::hours(2));
+        assert_eq!(balance.periods[3].expected, Duration::zero());
+        assert_eq!(balance.balance, Duration::hours(18 - 32));
+
+        storage
+            .set_setting(settings::BALANCE_START, Some("08.03.2023"))
+            .unwrap();
+        let balance = flex_time_balance(&storage, None, None, BalancePeriod::Week)
+            .unwrap()
+            .unwrap();
+        assert_eq!(balance.periods.len(), 1);
+        assert_eq!(balance.periods[0].name, "2023-W10");
+        assert_eq!(balance.balance, Duration::hours(-16));
+    }
+
+    #[test]
+    fn vacation_balance_test() {
+        let storage = storage();
+        storage.set_vacation_entitlement(2022, 25.0, None).unwrap();
+        storage.set_vacation_entitlement(2023, 20.0, None).unwrap();
+        storage
+            .set_setting(settings::VACATION_CARRY_OVER_MAX, Some("5"))
+            .unwrap();
+        let balance = vacation_balance(&storage, 2023).unwrap();
+        assert_eq!(balance.carry_over, 5.0);
+        assert_eq!(balance.used, 1.0);
+        assert_eq!(balance.remaining(), 24.0);
+
+        storage
+            .set_vacation_entitlement(2023, 20.0, Some(2.0))
+            .unwrap();
+        assert_eq!(vacation_balance(&storage, 2023).unwrap().remaining(), 21.0);
+    }
+}
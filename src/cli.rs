@@ -1,4 +1,4 @@
-use chrono::{Date, DateTime, NaiveDate, Utc};
+use chrono::{Date, DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 
 #[derive(Debug, Clone, Copy)]
 pub struct CliDate(pub Date<Utc>);
@@ -41,8 +41,75 @@ impl clap::builder::TypedValueParser for CliDateValueParser {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct CliTime(pub NaiveTime);
+
+impl clap::builder::ValueParserFactory for CliTime {
+    type Parser = CliTimeValueParser;
+
+    fn value_parser() -> Self::Parser {
+        CliTimeValueParser
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CliTimeValueParser;
+
+impl clap::builder::TypedValueParser for CliTimeValueParser {
+    type Value = CliTime;
+    fn parse_ref(
+        &self,
+        _cmd: &clap::Command,
+        _arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        let str = value.to_string_lossy().into_owned();
+        let result = NaiveTime::parse_from_str(&str, "%H:%M");
+        match result {
+            Ok(time) => Ok(CliTime(time)),
+            Err(err) => Err(clap::Error::raw(clap::ErrorKind::Format, err)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CliDateTime(pub DateTime<Utc>);
+
+impl clap::builder::ValueParserFactory for CliDateTime {
+    type Parser = CliDateTimeValueParser;
+
+    fn value_parser() -> Self::Parser {
+        CliDateTimeValueParser
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CliDateTimeValueParser;
+
+impl clap::builder::TypedValueParser for CliDateTimeValueParser {
+    type Value = CliDateTime;
+    fn parse_ref(
+        &self,
+        _cmd: &clap::Command,
+        _arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        let str = value.to_string_lossy().into_owned();
+        let result = NaiveDateTime::parse_from_str(&str, "%d.%m.%Y %H:%M").or_else(|_| {
+            NaiveDate::parse_from_str(&str, "%d.%m.%Y").map(|d| d.and_hms(23, 59, 59))
+        });
+        match result {
+            Ok(datetime) => Ok(CliDateTime(DateTime::from_utc(datetime, Utc))),
+            Err(err) => Err(clap::Error::raw(clap::ErrorKind::Format, err)),
+        }
+    }
+}
+
 #[derive(clap::Parser)]
-pub struct CLI {
+pub struct Cli {
+    #[clap(long, global = true, value_parser, value_name = "DATETIME")]
+    #[doc = "Use given time as current time. Format %d.%m.%Y %H:%M, end of day if time is omitted"]
+    pub now: Option<CliDateTime>,
     #[clap(subcommand)]
     pub command: Command,
 }
@@ -57,12 +124,28 @@ pub enum Command {
     New(NewArgs),
     #[doc = "Activate task"]
     Activate(ActivateArgs),
+    #[doc = "Mark task as done"]
+    Close(StatusArgs),
+    #[doc = "Archive task, archived tasks are hidden from list"]
+    Archive(StatusArgs),
+    #[doc = "Reopen done or archived task"]
+    Reopen(StatusArgs),
     #[doc = "Edit task description"]
     Edit(EditArgs),
+    #[doc = "Delete task"]
+    Delete(DeleteArgs),
+    #[doc = "Rename task"]
+    Rename(RenameArgs),
+    #[doc = "Merge source task into target task"]
+    Merge(MergeArgs),
     #[doc = "Generate report"]
     Report(ReportArgs),
+    #[doc = "Show overtime and flex-time balance"]
+    Balance(BalanceArgs),
     #[doc = "Show task description"]
     Show(ShowArgs),
+    #[doc = "Add note to task, to current task if only text is given"]
+    Note(NoteArgs),
     #[doc = "Manulay add task time range"]
     AddRange(AddRangeArgs),
     #[doc = "Add vacation"]
@@ -71,6 +154,112 @@ pub enum Command {
     VacationRemove(VacationRemoveArgs),
     #[doc = "List vacations"]
     VacationList(VacationListArgs),
+    #[doc = "Set vacation days entitlement of the year"]
+    VacationEntitlement(VacationEntitlementArgs),
+    #[doc = "Show used and remaining vacation days of the year"]
+    VacationBalance(VacationBalanceArgs),
+    #[doc = "Manage absences of any kind"]
+    #[clap(subcommand)]
+    Absence(AbsenceCommand),
+    #[doc = "Show or set workpackage budget"]
+    WorkpackageBudget(WorkpackageBudgetArgs),
+    #[doc = "Show or change settings"]
+    Config(ConfigArgs),
+    #[doc = "Check time ranges consistency"]
+    Doctor(DoctorArgs),
+    #[doc = "Undo last operation"]
+    Undo,
+    #[doc = "Redo last undone operation"]
+    Redo,
+}
+
+#[derive(clap::Parser)]
+pub struct DoctorArgs {
+    #[clap(short, long, value_parser)]
+    #[doc = "Apply suggested fixes"]
+    pub fix: bool,
+    #[clap(short, long, value_parser)]
+    #[doc = "Ask before applying each fix"]
+    pub interactive: bool,
+}
+
+#[derive(clap::Parser)]
+pub struct WorkpackageBudgetArgs {
+    #[clap(value_parser)]
+    #[doc = "Workpackage"]
+    pub workpackage: String,
+    #[clap(value_parser, value_name = "HOURS")]
+    #[doc = "Budget in hours"]
+    pub budget: Option<f64>,
+    #[clap(long, value_parser)]
+    #[doc = "Drop budget value"]
+    pub drop: bool,
+}
+
+#[derive(clap::Parser)]
+pub struct ConfigArgs {
+    #[clap(value_parser)]
+    #[doc = "Setting name, all settings are listed if not set"]
+    pub key: Option<String>,
+    #[clap(value_parser)]
+    #[doc = "New setting value"]
+    pub value: Option<String>,
+    #[clap(long, value_parser)]
+    #[doc = "Reset setting to default value"]
+    pub unset: bool,
+}
+
+#[derive(clap::Parser)]
+pub struct VacationEntitlementArgs {
+    #[clap(value_parser)]
+    #[doc = "Year"]
+    pub year: i32,
+    #[clap(value_parser)]
+    #[doc = "Vacation days"]
+    pub days: f64,
+    #[clap(short, long, value_parser, value_name = "DAYS")]
+    #[doc = "Days carried over from the previous year, remaining days of previous year if not set"]
+    pub carry_over: Option<f64>,
+}
+
+#[derive(clap::Parser)]
+pub struct VacationBalanceArgs {
+    #[clap(value_parser)]
+    #[doc = "Year, current year if not set"]
+    pub year: Option<i32>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum CliAbsenceKind {
+    Vacation,
+    Sick,
+    Training,
+    Parental,
+    Other,
+}
+
+#[derive(clap::Subcommand)]
+pub enum AbsenceCommand {
+    #[doc = "Add absence"]
+    Add(AbsenceAddArgs),
+    #[doc = "Show absence days per kind and remaining vacation days"]
+    Summary(AbsenceSummaryArgs),
+}
+
+#[derive(clap::Parser)]
+pub struct AbsenceAddArgs {
+    #[clap(flatten)]
+    pub range: VacationAddArgs,
+    #[clap(short, long, value_enum, default_value = "vacation")]
+    #[doc = "Absence kind"]
+    pub kind: CliAbsenceKind,
+}
+
+#[derive(clap::Parser)]
+pub struct AbsenceSummaryArgs {
+    #[clap(short, long, value_parser)]
+    #[doc = "Year, current year if not set"]
+    pub year: Option<i32>,
 }
 
 #[derive(clap::Parser)]
@@ -79,6 +268,15 @@ pub struct VacationListArgs {
     pub since: Option<CliDate>,
     #[clap(short, long, value_parser)]
     pub till: Option<CliDate>,
+    #[clap(short, long, value_enum)]
+    #[doc = "List only absences of the kind"]
+    pub kind: Option<CliAbsenceKind>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum HalfDay {
+    Morning,
+    Afternoon,
 }
 
 #[derive(clap::Parser)]
@@ -87,6 +285,18 @@ pub struct VacationAddArgs {
     pub since: CliDate,
     #[clap(short, long, value_parser)]
     pub till: CliDate,
+    #[clap(long, value_enum, conflicts_with_all = &["from", "to"])]
+    #[doc = "Take only half of the day, since and till must be the same date"]
+    pub half: Option<HalfDay>,
+    #[clap(long, value_parser, value_name = "TIME")]
+    #[doc = "Time vacation starts at on since date. Format %H:%M"]
+    pub from: Option<CliTime>,
+    #[clap(long, value_parser, value_name = "TIME")]
+    #[doc = "Time vacation ends at on till date. Format %H:%M"]
+    pub to: Option<CliTime>,
+    #[clap(short, long, value_parser)]
+    #[doc = "Merge with overlapping vacations of the same kind"]
+    pub merge: bool,
 }
 
 #[derive(clap::Parser)]
@@ -100,6 +310,9 @@ pub struct ListArgs {
     #[clap(short, long, value_parser, value_name = "INT")]
     #[doc = "If set first *num_tasks*"]
     pub num_tasks: Option<usize>,
+    #[clap(short, long, value_parser)]
+    #[doc = "Include archived tasks"]
+    pub all: bool,
 }
 
 #[derive(clap::Parser)]
@@ -109,24 +322,84 @@ pub struct ActivateArgs {
     pub task_id: String,
 }
 
+#[derive(clap::Parser)]
+pub struct StatusArgs {
+    #[clap(value_parser)]
+    #[doc = "Task id"]
+    pub task_id: String,
+}
+
+#[derive(clap::Parser)]
+pub struct DeleteArgs {
+    #[clap(value_parser)]
+    #[doc = "Task id"]
+    pub task_id: String,
+    #[clap(long, value_parser)]
+    #[doc = "Delete task time ranges too"]
+    pub cascade: bool,
+}
+
+#[derive(clap::Parser)]
+pub struct RenameArgs {
+    #[clap(value_parser)]
+    #[doc = "Task id"]
+    pub task_id: String,
+    #[clap(value_parser)]
+    #[doc = "New task id"]
+    pub new_task_id: String,
+}
+
+#[derive(clap::Parser)]
+pub struct MergeArgs {
+    #[clap(value_parser)]
+    #[doc = "Task id to merge, it is deleted after merge"]
+    pub source: String,
+    #[clap(value_parser)]
+    #[doc = "Task id to merge into"]
+    pub target: String,
+}
+
 #[derive(clap::Parser)]
 pub struct ShowArgs {
     #[clap(value_parser)]
     #[doc = "Task id"]
     pub task_id: String,
+    #[clap(long, value_parser)]
+    #[doc = "Show history of task changes"]
+    pub history: bool,
+}
+
+#[derive(clap::Parser)]
+pub struct NoteArgs {
+    #[clap(value_parser, value_name = "TASK_OR_TEXT")]
+    #[doc = "Task id if TEXT is set, otherwise note text"]
+    pub task_or_text: String,
+    #[clap(value_parser, value_name = "TEXT")]
+    #[doc = "Note text"]
+    pub text: Option<String>,
+    #[clap(short, long, value_parser)]
+    #[doc = "Attach note to the active time range of the task"]
+    pub attach: bool,
 }
 
 #[derive(clap::Parser)]
+#[clap(group(clap::ArgGroup::new("bounds").required(true).multiple(true).args(&["since", "till"])))]
 pub struct AddRangeArgs {
     #[clap(value_parser)]
     #[doc = "Task id"]
     pub task_id: String,
     #[clap(short, long, value_parser, value_name = "SINCE_DATE")]
-    #[doc = "Date since generate report. Format %d-%m-%Y"]
+    #[doc = "Start of the range at the beginning of the day. Format %d.%m.%Y"]
     pub since: Option<CliDate>,
     #[clap(short, long, value_parser, value_name = "TILL_DATE")]
-    #[doc = "Date till generate report. Format %d-%m-%Y"]
+    #[doc = "End of the range at the end of the day, stops the open range without --since. Format %d.%m.%Y"]
     pub till: Option<CliDate>,
+    #[clap(long, value_parser, requires_all = &["since", "till"], conflicts_with = "replace")]
+    #[doc = "Add only parts of the range not overlapping with other ranges"]
+    pub split: bool,
+    #[clap(long, value_parser, requires_all = &["since", "till"])]
+    #[doc = "Cut overlapping parts out of other ranges"]
+    pub replace: bool,
 }
 
 #[derive(clap::Parser)]
@@ -138,9 +411,109 @@ pub struct ReportArgs {
     #[doc = "Date till generate report. Format %d-%m-%Y"]
     pub till: CliDate,
 
-    #[clap(short, long, value_parser)]
-    #[doc = "If set print report in csv format"]
+    #[clap(short, long, value_parser, conflicts_with = "format")]
+    #[doc = "If set print report in csv format, same as --format csv"]
     pub csv: bool,
+
+    #[clap(short, long, value_enum, default_value = "table")]
+    #[doc = "Output format of printed report"]
+    pub format: ReportFormat,
+
+    #[clap(long, value_parser, value_name = "FILE", conflicts_with_all = &["csv", "format"])]
+    #[doc = "Write report with absences and vacations to Excel workbook instead of printing it. OpenDocument (ODS) export is not supported"]
+    pub xlsx: Option<std::path::PathBuf>,
+
+    #[clap(long, value_parser, conflicts_with_all = &["csv", "format", "xlsx"])]
+    #[doc = "Render report through Tera template file or built-in template: timesheet.txt, timesheet.md, timesheet.html"]
+    pub template: Option<String>,
+
+    #[clap(short, long, value_parser)]
+    #[doc = "Add column with task notes from the report period"]
+    pub notes: bool,
+
+    #[clap(short, long, value_parser)]
+    #[doc = "Add task and workpackage budget columns"]
+    pub budget: bool,
+
+    #[clap(long, value_enum, default_value = "normalized")]
+    #[doc = "Hours to show: tracked wall-clock time, hours inside working calendar or both"]
+    pub hours: HoursKind,
+
+    #[clap(short, long, value_parser)]
+    #[doc = "Add table with absence days per month and kind"]
+    pub absences: bool,
+
+    #[clap(short, long, value_parser)]
+    #[doc = "Attribute hours to workpackage valid at the time of work"]
+    pub wp_history: bool,
+
+    #[clap(long, value_parser)]
+    #[doc = "Omit totals rows, e.g. for further processing of csv output. Workbook written by \
+             --xlsx gets no SUM formulas then"]
+    pub no_totals: bool,
+
+    #[clap(long, value_parser, value_name = "MINUTES")]
+    #[doc = "Add column with total hours rounded to increments of minutes, month columns stay \
+             exact. No rounding if 0"]
+    pub round: Option<u32>,
+
+    #[clap(long, value_enum)]
+    #[doc = "Direction of rounding"]
+    pub round_mode: Option<RoundingMode>,
+
+    #[clap(long, value_enum)]
+    #[doc = "Round every range, day or total of a task"]
+    pub round_scope: Option<RoundingScope>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Table,
+    Csv,
+    Json,
+    Markdown,
+    Html,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum RoundingMode {
+    Up,
+    Down,
+    Nearest,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum RoundingScope {
+    Range,
+    Day,
+    Task,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum HoursKind {
+    Raw,
+    Normalized,
+    Both,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum BalancePeriod {
+    Day,
+    Week,
+    Month,
+}
+
+#[derive(clap::Parser)]
+pub struct BalanceArgs {
+    #[clap(short, long, value_parser, value_name = "SINCE_DATE")]
+    #[doc = "Date since show balance. Format %d.%m.%Y"]
+    pub since: Option<CliDate>,
+    #[clap(short, long, value_parser, value_name = "TILL_DATE")]
+    #[doc = "Date till show balance, today if not set. Format %d.%m.%Y"]
+    pub till: Option<CliDate>,
+    #[clap(short, long, value_enum, default_value = "week")]
+    #[doc = "Balance period"]
+    pub by: BalancePeriod,
 }
 
 #[derive(clap::Parser)]
@@ -164,6 +537,10 @@ pub struct NewArgs {
     #[clap(short, long, value_parser, value_name = "OBJECTIVE")]
     #[doc = "Objective"]
     pub objective: Option<String>,
+
+    #[clap(short, long, value_parser, value_name = "HOURS")]
+    #[doc = "Estimated hours"]
+    pub budget: Option<f64>,
 }
 
 #[derive(clap::Parser, Debug)]
@@ -203,4 +580,12 @@ pub struct EditArgs {
     #[clap(long, value_parser)]
     #[doc = "Drop objective value"]
     pub drop_objective: bool,
+
+    #[clap(short, long, value_parser, value_name = "HOURS")]
+    #[doc = "Set estimated hours to new value"]
+    pub budget: Option<f64>,
+
+    #[clap(long, value_parser)]
+    #[doc = "Drop estimated hours value"]
+    pub drop_budget: bool,
 }
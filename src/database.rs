@@ -1,33 +1,44 @@
+//! Sqlite storage of tasks, time range records, notes, absences and settings.
+
 use rusqlite::named_params;
 use rusqlite::Connection;
 use rusqlite::OptionalExtension;
 use rusqlite::ToSql;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::error::Error;
 use crate::error::Result;
-use crate::time_ranges::{from_timestamp, now_timestamp, to_timestamp, DateTime, TimeRange};
+use crate::time_ranges::{
+    from_timestamp, merge_ranges, to_timestamp, CalendarCombination, Clock, DateTime, SystemClock,
+    TimeRange,
+};
 
-pub(crate) struct Database {
+/// Task tracking data stored in the local SQLite database.
+pub struct Database {
     connection: Connection,
+    clock: Box<dyn Clock>,
 }
 
+/// `start_or_stop` value of a range start record.
 pub static START_VALUE: i64 = 1;
+/// `start_or_stop` value of a range stop record.
 pub static STOP_VALUE: i64 = 0;
 
-static CREATE_TASK_TABLE: &'static str = "
+static CREATE_TASK_TABLE: &str = "
 CREATE TABLE IF NOT EXISTS Task (
     task_id TEXT PRIMARY KEY,
     url TEXT,
     title TEXT,
     workpackage TEXT,
     objective TEXT,
-    last_update TEXT NOT NULL
+    last_update TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'open',
+    budget REAL
 );
 ";
 
-static CREATE_TASK_TIME_RANGES: &'static str = "
+static CREATE_TASK_TIME_RANGES: &str = "
 CREATE TABLE IF NOT EXISTS TaskTimeRanges (
     task_id TEXT NOT NULL,
     timestamp INTEGER NOT NULL,
@@ -39,26 +50,337 @@ CREATE INDEX IF NOT EXISTS RangesTimestamp ON TaskTimeRanges (
 );
 ";
 
-static CREATE_VACATIONS: &'static str = "
+static CREATE_VACATIONS: &str = "
 CREATE TABLE IF NOT EXISTS Vacations (
     vacation_id INTEGER PRIMARY KEY AUTOINCREMENT,
     start_timestemp INTEGER NOT NULL,
-    end_timestemp INTEGER NOT NULL
+    end_timestemp INTEGER NOT NULL,
+    kind TEXT NOT NULL DEFAULT 'vacation'
+);
+";
+
+static CREATE_NOTES: &str = "
+CREATE TABLE IF NOT EXISTS Notes (
+    note_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    task_id TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    range_start INTEGER,
+    text TEXT NOT NULL,
+    FOREIGN KEY(task_id) REFERENCES Task(task_id)
+);
+";
+
+static CREATE_WORKPACKAGES: &str = "
+CREATE TABLE IF NOT EXISTS Workpackages (
+    workpackage TEXT PRIMARY KEY,
+    budget REAL
+);
+";
+
+static CREATE_SETTINGS: &str = "
+CREATE TABLE IF NOT EXISTS Settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+";
+
+static CREATE_VACATION_ENTITLEMENTS: &str = "
+CREATE TABLE IF NOT EXISTS VacationEntitlements (
+    year INTEGER PRIMARY KEY,
+    days REAL NOT NULL,
+    carry_over REAL
+);
+";
+
+static CREATE_TASK_HISTORY: &str = "
+CREATE TABLE IF NOT EXISTS TaskHistory (
+    change_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    task_id TEXT NOT NULL,
+    field TEXT NOT NULL,
+    old_value TEXT,
+    new_value TEXT,
+    timestamp INTEGER NOT NULL,
+    FOREIGN KEY(task_id) REFERENCES Task(task_id)
+);
+";
+
+static CREATE_OPERATIONS: &str = "
+CREATE TABLE IF NOT EXISTS Operations (
+    op_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp INTEGER NOT NULL,
+    description TEXT NOT NULL,
+    state TEXT NOT NULL DEFAULT 'done'
+);
+";
+
+static CREATE_OPERATION_LOG: &str = "
+CREATE TABLE IF NOT EXISTS OperationLog (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    op_id INTEGER NOT NULL,
+    sql TEXT NOT NULL,
+    FOREIGN KEY(op_id) REFERENCES Operations(op_id) ON DELETE CASCADE
 );
 ";
 
+static CREATE_JOURNAL_STATE: &str = "
+CREATE TEMP TABLE IF NOT EXISTS JournalState (
+    active_op INTEGER
+);
+";
+
+/// Number of operations kept in the journal for undo.
+const JOURNAL_SIZE: i64 = 100;
+
+/// Result of [`Database::activate_task`].
 pub enum ActivationStatus {
+    /// Task was active already.
     AlreadyActive,
+    /// Task was activated, no other task was active.
     Activated,
+    /// Task was activated and the task with the id was stopped.
     Deactivated(String),
 }
 
+/// Result of [`Database::delete_task`].
+pub enum DeleteStatus {
+    /// Task was deleted.
+    Deleted,
+    /// Task does not exist.
+    NotFound,
+    /// Task was not deleted because it has the number of time ranges.
+    HasRanges(usize),
+}
+
+/// Result of [`Database::rename_task`].
+pub enum RenameStatus {
+    /// Task was renamed.
+    Renamed,
+    /// Task does not exist.
+    NotFound,
+    /// Task with the new id exists already.
+    AlreadyExists,
+}
+
+/// Result of [`Database::merge_tasks`].
+pub enum MergeStatus {
+    /// Tasks were merged.
+    Merged,
+    /// Source task does not exist.
+    SourceNotFound,
+    /// Target task does not exist.
+    TargetNotFound,
+    /// Source and target are the same task.
+    SameTask,
+    /// Task has a range which is never stopped and is not the current one, merging
+    /// would join it with all later ranges.
+    OpenRange(String),
+}
+
+/// Lifecycle status of a task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskStatus {
+    /// Task is worked on and can be activated.
+    Open,
+    /// Task is finished.
+    Done,
+    /// Task is hidden from the task list.
+    Archived,
+}
+
+impl TaskStatus {
+    /// Value stored in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskStatus::Open => "open",
+            TaskStatus::Done => "done",
+            TaskStatus::Archived => "archived",
+        }
+    }
+}
+
+impl std::fmt::Display for TaskStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl rusqlite::ToSql for TaskStatus {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        self.as_str().to_sql()
+    }
+}
+
+impl rusqlite::types::FromSql for TaskStatus {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        match value.as_str()? {
+            "open" => Ok(TaskStatus::Open),
+            "done" => Ok(TaskStatus::Done),
+            "archived" => Ok(TaskStatus::Archived),
+            _ => Err(rusqlite::types::FromSqlError::InvalidType),
+        }
+    }
+}
+
+/// Kind of absence stored in `Vacations` table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AbsenceKind {
+    /// Paid vacation counted against entitlement.
+    Vacation,
+    /// Sick leave.
+    Sick,
+    /// Training or conference.
+    Training,
+    /// Parental leave.
+    Parental,
+    /// Any other absence.
+    Other,
+}
+
+impl AbsenceKind {
+    /// All kinds in display order.
+    pub const ALL: [AbsenceKind; 5] = [
+        AbsenceKind::Vacation,
+        AbsenceKind::Sick,
+        AbsenceKind::Training,
+        AbsenceKind::Parental,
+        AbsenceKind::Other,
+    ];
+
+    /// Value stored in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AbsenceKind::Vacation => "vacation",
+            AbsenceKind::Sick => "sick",
+            AbsenceKind::Training => "training",
+            AbsenceKind::Parental => "parental",
+            AbsenceKind::Other => "other",
+        }
+    }
+}
+
+impl std::fmt::Display for AbsenceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl rusqlite::ToSql for AbsenceKind {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        self.as_str().to_sql()
+    }
+}
+
+impl rusqlite::types::FromSql for AbsenceKind {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        let value = value.as_str()?;
+        AbsenceKind::ALL
+            .into_iter()
+            .find(|k| k.as_str() == value)
+            .ok_or(rusqlite::types::FromSqlError::InvalidType)
+    }
+}
+
+/// Absence of any kind.
+#[derive(Clone)]
+pub struct Absence {
+    /// Row id of the absence.
+    pub vacation_id: i64,
+    /// First moment of the absence.
+    pub start: DateTime,
+    /// Last moment of the absence.
+    pub end: DateTime,
+    /// Kind of the absence.
+    pub kind: AbsenceKind,
+}
+
+/// Timestamped note of a task.
+#[derive(Clone)]
+pub struct Note {
+    /// Row id of the note.
+    pub note_id: i64,
+    /// Task the note belongs to.
+    pub task_id: String,
+    /// Time the note was added.
+    pub timestamp: DateTime,
+    /// Start of the time range note is attached to
+    pub range_start: Option<DateTime>,
+    /// Text of the note.
+    pub text: String,
+}
+
+/// Single start or stop record of task time ranges.
+#[derive(Debug, Clone)]
+pub struct RangeEvent {
+    /// Row id of the record.
+    pub rowid: i64,
+    /// Task of the record.
+    pub task_id: String,
+    /// Time of the record.
+    pub timestamp: DateTime,
+    /// [`START_VALUE`] or [`STOP_VALUE`].
+    pub start_or_stop: i64,
+}
+
+/// Change of time range records.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RangeFix {
+    /// Deletes the record with the row id.
+    Delete(i64),
+    /// Moves the record with the row id to the time.
+    Move(i64, DateTime),
+    /// Adds new record.
+    Insert {
+        /// Task of the record.
+        task_id: String,
+        /// Time of the record.
+        timestamp: DateTime,
+        /// [`START_VALUE`] or [`STOP_VALUE`].
+        start_or_stop: i64,
+    },
+    /// Creates task with the id and no fields.
+    CreateTask(String),
+}
+
+/// Journaled operation which can be undone or redone.
+pub struct Operation {
+    /// Id of the operation.
+    pub op_id: i64,
+    /// Time the operation was done.
+    pub timestamp: DateTime,
+    /// Command line of the operation.
+    pub description: String,
+}
+
+/// Change of task field value.
+#[derive(Clone)]
+pub struct TaskChange {
+    /// Name of the changed field.
+    pub field: String,
+    /// Value before the change.
+    pub old_value: Option<String>,
+    /// Value after the change.
+    pub new_value: Option<String>,
+    /// Time of the change.
+    pub timestamp: DateTime,
+}
+
+/// Task with its description fields.
+#[derive(Clone)]
 pub struct Task {
+    /// Unique id of the task.
     pub task_id: String,
+    /// Link to the task in an issue tracker.
     pub url: Option<String>,
+    /// Short description.
     pub title: Option<String>,
+    /// Workpackage the task is accounted to.
     pub workpackage: Option<String>,
+    /// Objective the task contributes to.
     pub objective: Option<String>,
+    /// Lifecycle status.
+    pub status: TaskStatus,
+    /// Estimated hours
+    pub budget: Option<f64>,
 }
 
 struct StrToSql {
@@ -78,20 +400,278 @@ impl rusqlite::ToSql for StrToSql {
 }
 
 impl Database {
+    /// Opens database in the user data directory, creating and migrating tables if needed.
     pub fn open() -> Result<Self> {
+        Self::open_with_clock(Box::new(SystemClock))
+    }
+
+    /// Opens database like [`Database::open`] taking the current time from `clock`.
+    pub fn open_with_clock(clock: Box<dyn Clock>) -> Result<Self> {
         let filename = Self::get_db_file();
         let directory = filename.parent().unwrap();
         std::fs::create_dir_all(directory)?;
-        let connection = Connection::open(filename)?;
+        Self::open_path(filename, clock)
+    }
+
+    /// Opens database file at `path`, `":memory:"` opens a new in-memory database.
+    pub fn open_path(path: impl AsRef<Path>, clock: Box<dyn Clock>) -> Result<Self> {
+        let connection = Connection::open(path)?;
 
         connection.execute("PRAGMA foreign_keys = ON;", ())?;
         connection.execute(CREATE_TASK_TABLE, ())?;
         connection.execute(CREATE_TASK_TIME_RANGES, ())?;
         connection.execute(CREATE_VACATIONS, ())?;
+        connection.execute(CREATE_NOTES, ())?;
+        connection.execute(CREATE_WORKPACKAGES, ())?;
+        connection.execute(CREATE_SETTINGS, ())?;
+        connection.execute(CREATE_VACATION_ENTITLEMENTS, ())?;
+        connection.execute(CREATE_TASK_HISTORY, ())?;
 
-        Ok(Self {
-            connection: connection,
-        })
+        Self::add_column_if_missing(
+            &connection,
+            "Task",
+            "status",
+            "TEXT NOT NULL DEFAULT 'open'",
+        )?;
+        Self::add_column_if_missing(&connection, "Task", "budget", "REAL")?;
+        Self::add_column_if_missing(
+            &connection,
+            "Vacations",
+            "kind",
+            "TEXT NOT NULL DEFAULT 'vacation'",
+        )?;
+        Self::create_journal(&connection)?;
+
+        Ok(Self { connection, clock })
+    }
+
+    /// Clock used for timestamps of changes.
+    pub fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    /// Creates journal tables and temporary triggers recording SQL statements reverting
+    /// every change of data tables made while an operation is active.
+    fn create_journal(connection: &Connection) -> Result<()> {
+        connection.execute(CREATE_OPERATIONS, ())?;
+        connection.execute(CREATE_OPERATION_LOG, ())?;
+        connection.execute(CREATE_JOURNAL_STATE, ())?;
+        connection.execute("INSERT INTO temp.JournalState VALUES (NULL);", ())?;
+
+        const SQL_TABLES: &str = "
+            SELECT name FROM main.sqlite_master
+                WHERE type = 'table' AND name NOT LIKE 'sqlite_%'
+                    AND name NOT IN ('Operations', 'OperationLog');
+        ";
+        let tables: Vec<String> = {
+            let mut stmt = connection.prepare(SQL_TABLES)?;
+            let result: rusqlite::Result<Vec<String>> = stmt.query_map((), |r| r.get(0))?.collect();
+            result?
+        };
+        for table in tables {
+            let columns: Vec<String> = {
+                let mut stmt = connection.prepare(&format!("PRAGMA table_info({})", table))?;
+                let result: rusqlite::Result<Vec<String>> =
+                    stmt.query_map((), |r| r.get(1))?.collect();
+                result?
+            };
+            let names = columns.join(", ");
+            let old_values = columns
+                .iter()
+                .map(|c| format!("quote(OLD.{})", c))
+                .collect::<Vec<_>>()
+                .join(" || ', ' || ");
+            let old_assignments = columns
+                .iter()
+                .map(|c| format!("'{} = ' || quote(OLD.{})", c, c))
+                .collect::<Vec<_>>()
+                .join(" || ', ' || ");
+
+            let inverse = [
+                (
+                    "INSERT",
+                    format!("'DELETE FROM {} WHERE rowid = ' || NEW.rowid", table),
+                ),
+                (
+                    "DELETE",
+                    format!(
+                        "'INSERT INTO {} (rowid, {}) VALUES (' || OLD.rowid || ', ' || {} || ')'",
+                        table, names, old_values
+                    ),
+                ),
+                (
+                    "UPDATE",
+                    format!(
+                        "'UPDATE {} SET ' || {} || ' WHERE rowid = ' || NEW.rowid",
+                        table, old_assignments
+                    ),
+                ),
+            ];
+            for (event, sql) in inverse {
+                connection.execute(
+                    &format!(
+                        "CREATE TEMP TRIGGER IF NOT EXISTS Journal{table}{event}
+                            AFTER {event} ON main.{table}
+                            WHEN (SELECT active_op FROM temp.JournalState) IS NOT NULL
+                        BEGIN
+                            INSERT INTO OperationLog (op_id, sql)
+                                SELECT active_op, {sql} FROM temp.JournalState;
+                        END;",
+                        table = table,
+                        event = event,
+                        sql = sql
+                    ),
+                    (),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts recording changes as operation with the description.
+    pub fn begin_operation(&self, description: &str) -> Result<()> {
+        const SQL: &str = "
+            INSERT INTO Operations (timestamp, description) VALUES (:now, :description);
+        ";
+        self.connection.execute(
+            SQL,
+            named_params! {":now": self.clock.now_timestamp(), ":description": description},
+        )?;
+        self.set_active_operation(Some(self.connection.last_insert_rowid()))
+    }
+
+    /// Stops recording changes. Operations without changes are dropped, otherwise
+    /// undone operations can't be redone anymore.
+    pub fn finish_operation(&self) -> Result<()> {
+        const SQL_ACTIVE: &str = "
+            SELECT active_op FROM temp.JournalState;
+        ";
+        const SQL_EMPTY: &str = "
+            DELETE FROM Operations
+                WHERE op_id = :op_id AND NOT EXISTS (SELECT 1 FROM OperationLog WHERE op_id = :op_id);
+        ";
+        const SQL_UNDONE: &str = "
+            DELETE FROM Operations WHERE state = 'undone';
+        ";
+        const SQL_OLD: &str = "
+            DELETE FROM Operations WHERE op_id <= (SELECT MAX(op_id) FROM Operations) - :size;
+        ";
+
+        let op_id: Option<i64> = self.connection.query_row(SQL_ACTIVE, (), |r| r.get(0))?;
+        self.set_active_operation(None)?;
+        if let Some(op_id) = op_id {
+            if self
+                .connection
+                .execute(SQL_EMPTY, named_params! {":op_id": op_id})?
+                == 0
+            {
+                self.connection.execute(SQL_UNDONE, ())?;
+                self.connection
+                    .execute(SQL_OLD, named_params! {":size": JOURNAL_SIZE})?;
+            }
+        }
+        Ok(())
+    }
+
+    fn set_active_operation(&self, op_id: Option<i64>) -> Result<()> {
+        const SQL: &str = "
+            UPDATE temp.JournalState SET active_op = :op_id;
+        ";
+        self.connection
+            .execute(SQL, named_params! {":op_id": op_id})?;
+        Ok(())
+    }
+
+    /// Reverts the last done operation.
+    pub fn undo(&mut self) -> Result<Option<Operation>> {
+        const SQL: &str = "
+            SELECT op_id, timestamp, description FROM Operations
+                WHERE state = 'done' ORDER BY op_id DESC LIMIT 1;
+        ";
+        self.replay_operation(SQL, "undone")
+    }
+
+    /// Repeats the last undone operation.
+    pub fn redo(&mut self) -> Result<Option<Operation>> {
+        const SQL: &str = "
+            SELECT op_id, timestamp, description FROM Operations
+                WHERE state = 'undone' ORDER BY op_id LIMIT 1;
+        ";
+        self.replay_operation(SQL, "done")
+    }
+
+    /// Executes recorded statements of the selected operation in reverse order. Statements
+    /// reverting them are recorded instead, so the operation can be replayed back.
+    fn replay_operation(&mut self, select_sql: &str, state: &str) -> Result<Option<Operation>> {
+        const SQL_LOG: &str = "
+            SELECT seq, sql FROM OperationLog WHERE op_id = :op_id ORDER BY seq DESC;
+        ";
+        const SQL_DELETE: &str = "
+            DELETE FROM OperationLog WHERE seq = :seq;
+        ";
+        const SQL_STATE: &str = "
+            UPDATE Operations SET state = :state WHERE op_id = :op_id;
+        ";
+
+        let operation = self
+            .connection
+            .query_row(select_sql, (), |r| {
+                Ok(Operation {
+                    op_id: r.get(0)?,
+                    timestamp: from_timestamp(r.get(1)?),
+                    description: r.get(2)?,
+                })
+            })
+            .optional()?;
+        let operation = match operation {
+            None => return Ok(None),
+            Some(operation) => operation,
+        };
+
+        let tx = self.connection.transaction()?;
+        tx.execute("PRAGMA defer_foreign_keys = ON;", ())?;
+        let log: Vec<(i64, String)> = {
+            let mut stmt = tx.prepare(SQL_LOG)?;
+            let result: rusqlite::Result<Vec<(i64, String)>> = stmt
+                .query_map(named_params! {":op_id": operation.op_id}, |r| {
+                    Ok((r.get(0)?, r.get(1)?))
+                })?
+                .collect();
+            result?
+        };
+        tx.execute(
+            "UPDATE temp.JournalState SET active_op = :op_id;",
+            named_params! {":op_id": operation.op_id},
+        )?;
+        for (seq, sql) in log {
+            tx.execute(SQL_DELETE, named_params! {":seq": seq})?;
+            tx.execute(&sql, ())?;
+        }
+        tx.execute("UPDATE temp.JournalState SET active_op = NULL;", ())?;
+        tx.execute(
+            SQL_STATE,
+            named_params! {":state": state, ":op_id": operation.op_id},
+        )?;
+        tx.commit()?;
+
+        Ok(Some(operation))
+    }
+
+    fn add_column_if_missing(
+        connection: &Connection,
+        table: &str,
+        column: &str,
+        definition: &str,
+    ) -> Result<()> {
+        let mut stmt = connection.prepare(&format!("PRAGMA table_info({})", table))?;
+        let columns: rusqlite::Result<Vec<String>> = stmt.query_map((), |r| r.get(1))?.collect();
+        if !columns?.iter().any(|c| c == column) {
+            connection.execute(
+                &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+                (),
+            )?;
+        }
+        Ok(())
     }
 
     fn get_db_file() -> PathBuf {
@@ -103,9 +683,11 @@ impl Database {
         data_dir.join(company_name).join(application).join(filename)
     }
 
+    /// Returns id of the task with not stopped time range.
     pub fn get_current_task_id(&self) -> Result<Option<String>> {
-        const SQL: &'static str = "
-            SELECT task_id, timestamp, start_or_stop FROM TaskTimeRanges WHERE timestamp = (SELECT MAX(timestamp) FROM TaskTimeRanges);
+        const SQL: &str = "
+            SELECT task_id, timestamp, start_or_stop FROM TaskTimeRanges
+                ORDER BY timestamp DESC, rowid DESC LIMIT 1;
         ";
         let mut stmp = self.connection.prepare(SQL)?;
         let row: Option<(String, i64)> = stmp
@@ -125,21 +707,48 @@ impl Database {
         Ok(None)
     }
 
-    pub fn add_vacation(&self, start: DateTime, end: DateTime) -> Result<()> {
-        const SQL: &'static str = "
-            INSERT INTO Vacations (start_timestemp, end_timestemp) VALUES (:start, :end);
+    /// Adds absence from start till end.
+    pub fn add_vacation(&self, start: DateTime, end: DateTime, kind: AbsenceKind) -> Result<()> {
+        self.replace_vacations(&[], start, end, kind)
+    }
+
+    /// Deletes vacations with `vacation_ids` and adds new one in one transaction.
+    pub fn replace_vacations(
+        &self,
+        vacation_ids: &[i64],
+        start: DateTime,
+        end: DateTime,
+        kind: AbsenceKind,
+    ) -> Result<()> {
+        const SQL_DELETE: &str = "
+            DELETE FROM Vacations WHERE vacation_id = :vacation_id;
         ";
-        let mut stmp = self.connection.prepare(SQL)?;
-        stmp.execute(named_params! {
-            ":start": to_timestamp(&start),
-            ":end": to_timestamp(&end),
-        })?;
+        const SQL: &str = "
+            INSERT INTO Vacations (start_timestemp, end_timestemp, kind) VALUES (:start, :end, :kind);
+        ";
+        if start > end {
+            return Err(Error::InvalidRange);
+        }
+        let tx = self.connection.unchecked_transaction()?;
+        for vacation_id in vacation_ids {
+            tx.execute(SQL_DELETE, named_params! {":vacation_id": vacation_id})?;
+        }
+        tx.execute(
+            SQL,
+            named_params! {
+                ":start": to_timestamp(&start),
+                ":end": to_timestamp(&end),
+                ":kind": kind,
+            },
+        )?;
+        tx.commit()?;
 
         Ok(())
     }
 
+    /// Deletes absence by id.
     pub fn delete_vacation(&self, vacation_id: i64) -> Result<()> {
-        const SQL: &'static str = "
+        const SQL: &str = "
             DELETE FROM Vacations WHERE vacation_id = :vacation_id;
         ";
         let mut stmp = self.connection.prepare(SQL)?;
@@ -150,8 +759,50 @@ impl Database {
         Ok(())
     }
 
+    /// Sets vacation days of the year, carry-over is computed from the previous
+    /// year if not set.
+    pub fn set_vacation_entitlement(
+        &self,
+        year: i32,
+        days: f64,
+        carry_over: Option<f64>,
+    ) -> Result<()> {
+        const SQL: &str = "
+            INSERT INTO VacationEntitlements (year, days, carry_over) VALUES (:year, :days, :carry_over)
+                ON CONFLICT(year) DO UPDATE SET days = excluded.days, carry_over = excluded.carry_over;
+        ";
+        check_days(Some(days))?;
+        check_days(carry_over)?;
+        self.connection.execute(
+            SQL,
+            named_params! {":year": year, ":days": days, ":carry_over": carry_over},
+        )?;
+        Ok(())
+    }
+
+    /// Returns vacation days and carry-over days of the year.
+    pub fn get_vacation_entitlement(&self, year: i32) -> Result<Option<(f64, Option<f64>)>> {
+        const SQL: &str = "
+            SELECT days, carry_over FROM VacationEntitlements WHERE year = :year;
+        ";
+        self.connection
+            .query_row(SQL, named_params! {":year": year}, |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .optional()
+            .map_err(|e| e.into())
+    }
+
+    /// Holiday calendar including all absences.
+    pub fn work_calendar(&self) -> Result<CalendarCombination> {
+        Ok(CalendarCombination::holydays_and_vacations(
+            self.get_vacations()?,
+        ))
+    }
+
+    /// Returns time ranges of all absences.
     pub fn get_vacations(&self) -> Result<Vec<(DateTime, DateTime)>> {
-        const SQL: &'static str = "
+        const SQL: &str = "
             SELECT start_timestemp, end_timestemp FROM Vacations;
         ";
 
@@ -167,28 +818,35 @@ impl Database {
         result.map_err(|e| e.into())
     }
 
+    /// Returns absences overlapping `[start, end]`, all kinds if `kind` is not set.
     pub fn list_vacations(
         &self,
         start: DateTime,
         end: DateTime,
-    ) -> Result<Vec<(i64, DateTime, DateTime)>> {
-        const SQL: &'static str = "
-            SELECT vacation_id, start_timestemp, end_timestemp FROM Vacations WHERE start_timestemp >= :start AND end_timestemp <= :end;
+        kind: Option<AbsenceKind>,
+    ) -> Result<Vec<Absence>> {
+        const SQL: &str = "
+            SELECT vacation_id, start_timestemp, end_timestemp, kind FROM Vacations
+            WHERE start_timestemp <= :end AND end_timestemp >= :start
+                AND (:kind IS NULL OR kind = :kind)
+            ORDER BY start_timestemp;
         ";
 
         let mut stmp = self.connection.prepare(SQL)?;
-        let result: rusqlite::Result<Vec<(i64, DateTime, DateTime)>> = stmp
+        let result: rusqlite::Result<Vec<Absence>> = stmp
             .query_map(
                 named_params! {
                     ":start": to_timestamp(&start),
                     ":end": to_timestamp(&end),
+                    ":kind": kind,
                 },
                 |r| {
-                    Ok((
-                        r.get(0)?,
-                        from_timestamp(r.get(1)?),
-                        from_timestamp(r.get(2)?),
-                    ))
+                    Ok(Absence {
+                        vacation_id: r.get(0)?,
+                        start: from_timestamp(r.get(1)?),
+                        end: from_timestamp(r.get(2)?),
+                        kind: r.get(3)?,
+                    })
                 },
             )?
             .collect();
@@ -196,21 +854,22 @@ impl Database {
         result.map_err(|e| e.into())
     }
 
-    pub fn list_tasks(&self, top_n: Option<usize>) -> Result<Vec<Task>> {
-        const SQL_NO_LIMIT: &'static str = "
-            SELECT task_id, title FROM Task ORDER BY last_update DESC;
-        ";
-        const SQL_LIMIT: &'static str = "
-            SELECT task_id, title FROM Task ORDER BY last_update DESC LIMIT :top_n;
-        ";
-
-        let mut stmt = self.connection.prepare(if top_n.is_some() {
-            SQL_LIMIT
+    /// Returns tasks ordered by last update, the most recent first.
+    pub fn list_tasks(&self, top_n: Option<usize>, with_archived: bool) -> Result<Vec<Task>> {
+        let where_block = if with_archived {
+            ""
         } else {
-            SQL_NO_LIMIT
-        })?;
+            " WHERE status != 'archived'"
+        };
+        let limit_block = if top_n.is_some() { " LIMIT :top_n" } else { "" };
+        let sql = format!(
+            "SELECT task_id, title, status FROM Task{} ORDER BY last_update DESC{};",
+            where_block, limit_block
+        );
 
-        let row_map = |r: &rusqlite::Row| Ok((r.get(0)?, r.get(1)?));
+        let mut stmt = self.connection.prepare(&sql)?;
+
+        let row_map = |r: &rusqlite::Row| Ok((r.get(0)?, r.get(1)?, r.get(2)?));
 
         let res_iter = if let Some(top_n) = top_n {
             stmt.query_map(named_params! {":top_n": top_n}, row_map)?
@@ -220,22 +879,25 @@ impl Database {
 
         let mut result = Vec::new();
         for row in res_iter {
-            let (task_id, title) = row?;
+            let (task_id, title, status) = row?;
             let task = Task {
-                task_id: task_id,
-                title: title,
+                task_id,
+                title,
                 url: None,
                 workpackage: None,
                 objective: None,
+                status,
+                budget: None,
             };
             result.push(task);
         }
         Ok(result)
     }
 
+    /// Returns task by id.
     pub fn get_task(&self, task_id: &str) -> Result<Option<Task>> {
-        const SQL: &'static str = "
-            SELECT task_id, url, title, workpackage, objective FROM Task WHERE task_id = :task_id;
+        const SQL: &str = "
+            SELECT task_id, url, title, workpackage, objective, status, budget FROM Task WHERE task_id = :task_id;
         ";
         let mut stmt = self.connection.prepare(SQL)?;
         stmt.query_row(named_params! {":task_id": task_id}, |r| {
@@ -245,12 +907,15 @@ impl Database {
                 title: r.get(2)?,
                 workpackage: r.get(3)?,
                 objective: r.get(4)?,
+                status: r.get(5)?,
+                budget: r.get(6)?,
             })
         })
         .optional()
         .map_err(|e| e.into())
     }
 
+    /// Creates new task.
     pub fn new_task(
         &self,
         task_id: &str,
@@ -258,11 +923,13 @@ impl Database {
         title: Option<&str>,
         wp: Option<&str>,
         o: Option<&str>,
+        budget: Option<f64>,
     ) -> Result<()> {
-        const SQL: &'static str = "
-        INSERT INTO Task (task_id, url, title, workpackage, objective, last_update)
-        VALUES (:task_id, :url, :title, :workpackage, :objective, :now);
+        const SQL: &str = "
+        INSERT INTO Task (task_id, url, title, workpackage, objective, budget, last_update)
+        VALUES (:task_id, :url, :title, :workpackage, :objective, :budget, :now);
         ";
+        check_budget(budget)?;
 
         let mut stmt = self.connection.prepare(SQL)?;
         stmt.insert(named_params! {
@@ -271,44 +938,101 @@ impl Database {
         ":title": title,
         ":workpackage": wp,
         ":objective": o,
-        ":now": now_timestamp()})
+        ":budget": budget,
+        ":now": self.clock.now_timestamp()})
             .map(|_| ())
-            .map_err(|e| e.into())
+            .map_err(|e| match e {
+                e if is_primary_key_error(&e) => Error::TaskExists,
+                e => e.into(),
+            })
     }
 
-    fn update_task_field(
+    fn update_task_field<T: ToSql>(
         &self,
         task_id: &str,
         field: &str,
-        value: Option<&str>,
+        value: Option<T>,
         drop: bool,
     ) -> Result<(bool, bool)> {
         if value.is_none() && !drop {
             return Ok((false, false));
         }
 
+        let tx = self.connection.unchecked_transaction()?;
+        let old_value: Option<Option<String>> = tx
+            .query_row(
+                &format!("SELECT CAST({} AS TEXT) FROM Task WHERE task_id = ?", field),
+                [task_id],
+                |r| r.get(0),
+            )
+            .optional()?;
+        let old_value = match old_value {
+            None => return Ok((false, true)),
+            Some(old_value) => old_value,
+        };
+
         let query = format!(
             "UPDATE Task SET {} = ?, last_update = ? WHERE task_id = ?",
             field
         );
-        let mut stmt = self.connection.prepare(&query)?;
-        let nrows = if drop {
-            stmt.execute([
-                &rusqlite::types::Null as &dyn ToSql,
-                &now_timestamp() as &dyn ToSql,
-                &StrToSql::new(task_id.to_string()) as &dyn ToSql,
-            ])?
-        } else {
-            stmt.execute([
-                &StrToSql::new(value.unwrap().to_string()) as &dyn ToSql,
-                &now_timestamp() as &dyn ToSql,
-                &StrToSql::new(task_id.to_string()) as &dyn ToSql,
-            ])?
+        let nrows = {
+            let mut stmt = tx.prepare(&query)?;
+            if drop {
+                stmt.execute([
+                    &rusqlite::types::Null as &dyn ToSql,
+                    &self.clock.now_timestamp() as &dyn ToSql,
+                    &StrToSql::new(task_id.to_string()) as &dyn ToSql,
+                ])?
+            } else {
+                stmt.execute([
+                    &value.unwrap() as &dyn ToSql,
+                    &self.clock.now_timestamp() as &dyn ToSql,
+                    &StrToSql::new(task_id.to_string()) as &dyn ToSql,
+                ])?
+            }
         };
+        let new_value: Option<String> = tx.query_row(
+            &format!("SELECT CAST({} AS TEXT) FROM Task WHERE task_id = ?", field),
+            [task_id],
+            |r| r.get(0),
+        )?;
+        if new_value != old_value {
+            record_change(
+                &tx,
+                task_id,
+                field,
+                old_value,
+                new_value,
+                self.clock.now_timestamp(),
+            )?;
+        }
+        tx.commit()?;
 
         Ok((nrows == 1, true))
     }
 
+    /// Returns changes of task fields ordered by time.
+    pub fn get_task_history(&self, task_id: &str) -> Result<Vec<TaskChange>> {
+        const SQL: &str = "
+            SELECT field, old_value, new_value, timestamp FROM TaskHistory
+                WHERE task_id = :task_id ORDER BY timestamp, change_id;
+        ";
+        let mut stmt = self.connection.prepare(SQL)?;
+        let result: rusqlite::Result<Vec<TaskChange>> = stmt
+            .query_map(named_params! {":task_id": task_id}, |r| {
+                Ok(TaskChange {
+                    field: r.get(0)?,
+                    old_value: r.get(1)?,
+                    new_value: r.get(2)?,
+                    timestamp: from_timestamp(r.get(3)?),
+                })
+            })?
+            .collect();
+        result.map_err(|e| e.into())
+    }
+
+    /// Updates or drops task fields. Returns whether the task was found and whether anything was requested to change.
+    #[allow(clippy::too_many_arguments)]
     pub fn update_task(
         &self,
         task_id: &str,
@@ -316,51 +1040,474 @@ impl Database {
         title: Option<&str>,
         wp: Option<&str>,
         o: Option<&str>,
+        budget: Option<f64>,
         drop_url: bool,
         drop_title: bool,
         drop_wp: bool,
         drop_o: bool,
+        drop_budget: bool,
     ) -> Result<(bool, bool)> {
+        check_budget(budget)?;
         let (found_url, was_url) = self.update_task_field(task_id, "url", url, drop_url)?;
         let (found_title, was_title) =
             self.update_task_field(task_id, "title", title, drop_title)?;
         let (found_wp, was_wp) = self.update_task_field(task_id, "workpackage", wp, drop_wp)?;
         let (found_o, was_o) = self.update_task_field(task_id, "objective", o, drop_o)?;
+        let (found_b, was_b) = self.update_task_field(task_id, "budget", budget, drop_budget)?;
         Ok((
-            found_url || found_title || found_wp || found_o,
-            was_url || was_title || was_wp | was_o,
+            found_url || found_title || found_wp || found_o || found_b,
+            was_url || was_title || was_wp || was_o || was_b,
         ))
     }
 
-    pub fn is_task_exist(&self, task_id: &str) -> Result<bool> {
-        const SQL: &'static str = "
-            SELECT task_id FROM Task WHERE task_id = :task_id;
+    /// Returns ids of all tasks in workpackage.
+    pub fn get_workpackage_tasks(&self, workpackage: &str) -> Result<Vec<String>> {
+        const SQL: &str = "
+            SELECT task_id FROM Task WHERE workpackage = :workpackage;
         ";
         let mut stmt = self.connection.prepare(SQL)?;
-        let result: Option<String> = stmt
-            .query_row(named_params! {":task_id": task_id}, |r| r.get(0))
+        let result: rusqlite::Result<Vec<String>> = stmt
+            .query_map(named_params! {":workpackage": workpackage}, |r| r.get(0))?
+            .collect();
+        result.map_err(|e| e.into())
+    }
+
+    /// Returns budget of the workpackage in hours.
+    pub fn get_workpackage_budget(&self, workpackage: &str) -> Result<Option<f64>> {
+        const SQL: &str = "
+            SELECT budget FROM Workpackages WHERE workpackage = :workpackage;
+        ";
+        let budget: Option<Option<f64>> = self
+            .connection
+            .query_row(SQL, named_params! {":workpackage": workpackage}, |r| {
+                r.get(0)
+            })
             .optional()?;
-        Ok(result.is_some())
+        Ok(budget.flatten())
+    }
+
+    /// Sets or drops budget of the workpackage.
+    pub fn set_workpackage_budget(&self, workpackage: &str, budget: Option<f64>) -> Result<()> {
+        const SQL: &str = "
+            INSERT INTO Workpackages (workpackage, budget) VALUES (:workpackage, :budget)
+                ON CONFLICT(workpackage) DO UPDATE SET budget = excluded.budget;
+        ";
+        check_budget(budget)?;
+        self.connection.execute(
+            SQL,
+            named_params! {":workpackage": workpackage, ":budget": budget},
+        )?;
+        Ok(())
+    }
+
+    /// Returns value of the setting if it was set.
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        const SQL: &str = "
+            SELECT value FROM Settings WHERE key = :key;
+        ";
+        self.connection
+            .query_row(SQL, named_params! {":key": key}, |r| r.get(0))
+            .optional()
+            .map_err(|e| e.into())
     }
 
+    /// Sets setting value, `None` removes setting.
+    pub fn set_setting(&self, key: &str, value: Option<&str>) -> Result<()> {
+        const SQL_SET: &str = "
+            INSERT INTO Settings (key, value) VALUES (:key, :value)
+                ON CONFLICT(key) DO UPDATE SET value = excluded.value;
+        ";
+        const SQL_UNSET: &str = "
+            DELETE FROM Settings WHERE key = :key;
+        ";
+        match value {
+            Some(value) => self
+                .connection
+                .execute(SQL_SET, named_params! {":key": key, ":value": value})?,
+            None => self
+                .connection
+                .execute(SQL_UNSET, named_params! {":key": key})?,
+        };
+        Ok(())
+    }
+
+    /// Changes task status and returns the previous one, `None` if task does not exist.
+    /// Closing or archiving the current task stops it.
+    pub fn set_task_status(
+        &mut self,
+        task_id: &str,
+        status: TaskStatus,
+    ) -> Result<Option<TaskStatus>> {
+        const SQL: &str = "
+            UPDATE Task SET status = :status, last_update = :now WHERE task_id = :task_id;
+        ";
+        const SQL_R: &str = "
+            INSERT INTO TaskTimeRanges (task_id, timestamp, start_or_stop)
+                VALUES (:task_id, :now, :value);
+        ";
+        let old_status = match self.get_task(task_id)? {
+            None => return Ok(None),
+            Some(task) => task.status,
+        };
+        if old_status == status {
+            return Ok(Some(old_status));
+        }
+        let stop =
+            status != TaskStatus::Open && self.get_current_task_id()?.as_deref() == Some(task_id);
+        let ns = self.clock.now_timestamp();
+        let tx = self.connection.transaction()?;
+        if stop {
+            tx.execute(
+                SQL_R,
+                named_params! {":task_id": task_id, ":now": ns, ":value": STOP_VALUE},
+            )?;
+        }
+        tx.execute(
+            SQL,
+            named_params! {":task_id": task_id, ":status": status, ":now": ns},
+        )?;
+        tx.commit()?;
+        Ok(Some(old_status))
+    }
+
+    /// Deletes task with its notes. Task with time ranges is deleted only if
+    /// `cascade` is set, in this case its ranges are deleted too.
+    pub fn delete_task(&mut self, task_id: &str, cascade: bool) -> Result<DeleteStatus> {
+        const SQL_COUNT: &str = "
+            SELECT COUNT(*) FROM TaskTimeRanges WHERE task_id = :task_id;
+        ";
+        const SQL_R: &str = "
+            DELETE FROM TaskTimeRanges WHERE task_id = :task_id;
+        ";
+        const SQL_N: &str = "
+            DELETE FROM Notes WHERE task_id = :task_id;
+        ";
+        const SQL_H: &str = "
+            DELETE FROM TaskHistory WHERE task_id = :task_id;
+        ";
+        const SQL_T: &str = "
+            DELETE FROM Task WHERE task_id = :task_id;
+        ";
+
+        let tx = self.connection.transaction()?;
+
+        let ranges: usize =
+            tx.query_row(SQL_COUNT, named_params! {":task_id": task_id}, |r| r.get(0))?;
+        if ranges > 0 && !cascade {
+            return Ok(DeleteStatus::HasRanges(ranges));
+        }
+
+        tx.execute(SQL_R, named_params! {":task_id": task_id})?;
+        tx.execute(SQL_N, named_params! {":task_id": task_id})?;
+        tx.execute(SQL_H, named_params! {":task_id": task_id})?;
+        let deleted = tx.execute(SQL_T, named_params! {":task_id": task_id})?;
+        if deleted != 1 {
+            return Ok(DeleteStatus::NotFound);
+        }
+
+        tx.commit()?;
+
+        Ok(DeleteStatus::Deleted)
+    }
+
+    /// Renames task and moves all its time ranges, notes and history to the new id.
+    pub fn rename_task(&mut self, task_id: &str, new_task_id: &str) -> Result<RenameStatus> {
+        const SQL_T: &str = "
+            UPDATE Task SET task_id = :new_task_id, last_update = :now WHERE task_id = :task_id;
+        ";
+        const SQL_R: &str = "
+            UPDATE TaskTimeRanges SET task_id = :new_task_id WHERE task_id = :task_id;
+        ";
+        const SQL_N: &str = "
+            UPDATE Notes SET task_id = :new_task_id WHERE task_id = :task_id;
+        ";
+        const SQL_H: &str = "
+            UPDATE TaskHistory SET task_id = :new_task_id WHERE task_id = :task_id;
+        ";
+
+        if self.get_task(task_id)?.is_none() {
+            return Ok(RenameStatus::NotFound);
+        }
+        if self.get_task(new_task_id)?.is_some() {
+            return Ok(RenameStatus::AlreadyExists);
+        }
+
+        let tx = self.connection.transaction()?;
+        // Task and its ranges are updated one after another, so foreign key
+        // check has to wait till commit.
+        tx.execute("PRAGMA defer_foreign_keys = ON;", ())?;
+        tx.execute(
+            SQL_T,
+            named_params! {
+                ":task_id": task_id,
+                ":new_task_id": new_task_id,
+                ":now": self.clock.now_timestamp(),
+            },
+        )?;
+        for sql in [SQL_R, SQL_N, SQL_H] {
+            tx.execute(
+                sql,
+                named_params! {":task_id": task_id, ":new_task_id": new_task_id},
+            )?;
+        }
+        record_change(
+            &tx,
+            new_task_id,
+            "task_id",
+            Some(task_id.to_owned()),
+            Some(new_task_id.to_owned()),
+            self.clock.now_timestamp(),
+        )?;
+        tx.commit()?;
+
+        Ok(RenameStatus::Renamed)
+    }
+
+    /// Moves time ranges and notes of `source` task to `target` task joining overlapping
+    /// ranges, fills empty fields of `target` from `source` and deletes `source`.
+    /// History of `source` is dropped, so moved ranges belong to workpackages of `target`.
+    /// Only the open range of the current task may be merged, other open ranges are refused.
+    pub fn merge_tasks(&mut self, source: &str, target: &str) -> Result<MergeStatus> {
+        const SQL_T: &str = "
+            UPDATE Task SET
+                url = COALESCE(url, :url),
+                title = COALESCE(title, :title),
+                workpackage = COALESCE(workpackage, :workpackage),
+                objective = COALESCE(objective, :objective),
+                budget = COALESCE(budget, :budget),
+                last_update = :now
+            WHERE task_id = :task_id;
+        ";
+        const SQL_DELETE_R: &str = "
+            DELETE FROM TaskTimeRanges WHERE task_id = :source OR task_id = :target;
+        ";
+        const SQL_INSERT_R: &str = "
+            INSERT INTO TaskTimeRanges (task_id, timestamp, start_or_stop)
+                VALUES (:task_id, :timestamp, :value);
+        ";
+        const SQL_N: &str = "
+            UPDATE Notes SET task_id = :target WHERE task_id = :source;
+        ";
+        const SQL_H: &str = "
+            DELETE FROM TaskHistory WHERE task_id = :source;
+        ";
+        const SQL_DELETE_T: &str = "
+            DELETE FROM Task WHERE task_id = :task_id;
+        ";
+
+        if source == target {
+            return Ok(MergeStatus::SameTask);
+        }
+        let source_task = match self.get_task(source)? {
+            None => return Ok(MergeStatus::SourceNotFound),
+            Some(task) => task,
+        };
+        let target_task = match self.get_task(target)? {
+            None => return Ok(MergeStatus::TargetNotFound),
+            Some(task) => task,
+        };
+
+        let current_task = self.get_current_task_id()?;
+        let mut ranges = Vec::new();
+        for task_id in [source, target] {
+            if let Some(task_ranges) = self
+                .select_time_ranges(Some(task_id), None, None)?
+                .remove(task_id)
+            {
+                // Only the last range of the current task may be open.
+                let open = task_ranges.iter().filter(|r| r.end.is_none()).count();
+                let current = current_task.as_deref() == Some(task_id);
+                if open > usize::from(current) {
+                    return Ok(MergeStatus::OpenRange(task_id.to_owned()));
+                }
+                ranges.extend(task_ranges);
+            }
+        }
+        let ranges = merge_ranges(ranges);
+
+        let tx = self.connection.transaction()?;
+        tx.execute(
+            SQL_T,
+            named_params! {
+                ":task_id": target,
+                ":url": source_task.url,
+                ":title": source_task.title,
+                ":workpackage": source_task.workpackage,
+                ":objective": source_task.objective,
+                ":budget": source_task.budget,
+                ":now": self.clock.now_timestamp(),
+            },
+        )?;
+        tx.execute(
+            SQL_DELETE_R,
+            named_params! {":source": source, ":target": target},
+        )?;
+        {
+            let mut stmt = tx.prepare(SQL_INSERT_R)?;
+            for range in ranges {
+                let points = [(range.start, START_VALUE), (range.end, STOP_VALUE)];
+                for (dt, value) in points {
+                    if let Some(dt) = dt {
+                        stmt.insert(named_params! {
+                            ":task_id": target,
+                            ":timestamp": to_timestamp(&dt),
+                            ":value": value,
+                        })?;
+                    }
+                }
+            }
+        }
+        tx.execute(SQL_N, named_params! {":source": source, ":target": target})?;
+        tx.execute(SQL_H, named_params! {":source": source})?;
+        let filled = [
+            ("url", &target_task.url, &source_task.url),
+            ("title", &target_task.title, &source_task.title),
+            (
+                "workpackage",
+                &target_task.workpackage,
+                &source_task.workpackage,
+            ),
+            ("objective", &target_task.objective, &source_task.objective),
+        ];
+        for (field, old, new) in filled {
+            if old.is_none() && new.is_some() {
+                record_change(
+                    &tx,
+                    target,
+                    field,
+                    None,
+                    new.clone(),
+                    self.clock.now_timestamp(),
+                )?;
+            }
+        }
+        if target_task.budget.is_none() && source_task.budget.is_some() {
+            record_change(
+                &tx,
+                target,
+                "budget",
+                None,
+                source_task.budget.map(|b| format!("{:?}", b)),
+                self.clock.now_timestamp(),
+            )?;
+        }
+        record_change(
+            &tx,
+            target,
+            "merged",
+            Some(source.to_owned()),
+            Some(target.to_owned()),
+            self.clock.now_timestamp(),
+        )?;
+        tx.execute(SQL_DELETE_T, named_params! {":task_id": source})?;
+        tx.commit()?;
+
+        Ok(MergeStatus::Merged)
+    }
+
+    /// Adds note to the task, optionally attached to the range started at `range_start`.
+    pub fn add_note(
+        &self,
+        task_id: &str,
+        text: &str,
+        range_start: Option<DateTime>,
+    ) -> Result<i64> {
+        const SQL: &str = "
+            INSERT INTO Notes (task_id, timestamp, range_start, text)
+                VALUES (:task_id, :now, :range_start, :text);
+        ";
+        let mut stmt = self.connection.prepare(SQL)?;
+        stmt.insert(named_params! {
+            ":task_id": task_id,
+            ":now": self.clock.now_timestamp(),
+            ":range_start": range_start.map(|dt| to_timestamp(&dt)),
+            ":text": text,
+        })
+        .map_err(|e| e.into())
+    }
+
+    /// Returns notes ordered by creation time. All filters are optional.
+    pub fn get_notes(
+        &self,
+        task_id: Option<&str>,
+        start_date: Option<DateTime>,
+        end_date: Option<DateTime>,
+    ) -> Result<Vec<Note>> {
+        const SQL: &str = "
+            SELECT note_id, task_id, timestamp, range_start, text FROM Notes
+            WHERE (:task_id IS NULL OR task_id = :task_id)
+                AND (:start IS NULL OR timestamp >= :start)
+                AND (:end IS NULL OR timestamp <= :end)
+            ORDER BY timestamp;
+        ";
+        let mut stmt = self.connection.prepare(SQL)?;
+        let result: rusqlite::Result<Vec<Note>> = stmt
+            .query_map(
+                named_params! {
+                    ":task_id": task_id,
+                    ":start": start_date.map(|dt| to_timestamp(&dt)),
+                    ":end": end_date.map(|dt| to_timestamp(&dt)),
+                },
+                |r| {
+                    let range_start: Option<i64> = r.get(3)?;
+                    Ok(Note {
+                        note_id: r.get(0)?,
+                        task_id: r.get(1)?,
+                        timestamp: from_timestamp(r.get(2)?),
+                        range_start: range_start.map(from_timestamp),
+                        text: r.get(4)?,
+                    })
+                },
+            )?
+            .collect();
+
+        result.map_err(|e| e.into())
+    }
+
+    /// Returns start of the open time range if task is the current one.
+    pub fn get_active_range_start(&self, task_id: &str) -> Result<Option<DateTime>> {
+        const SQL: &str = "
+            SELECT MAX(timestamp) FROM TaskTimeRanges WHERE task_id = :task_id AND start_or_stop = :start;
+        ";
+        if self.get_current_task_id()?.as_deref() != Some(task_id) {
+            return Ok(None);
+        }
+        let timestamp: Option<i64> = self.connection.query_row(
+            SQL,
+            named_params! {":task_id": task_id, ":start": START_VALUE},
+            |r| r.get(0),
+        )?;
+        Ok(timestamp.map(from_timestamp))
+    }
+
+    /// Returns time of the earliest time range record.
+    pub fn get_first_range_time(&self) -> Result<Option<DateTime>> {
+        const SQL: &str = "
+            SELECT MIN(timestamp) FROM TaskTimeRanges;
+        ";
+        let timestamp: Option<i64> = self.connection.query_row(SQL, (), |r| r.get(0))?;
+        Ok(timestamp.map(from_timestamp))
+    }
+
+    /// Adds start or stop record of the task at `dt` or now.
     pub fn update_time_ranges(
         &mut self,
         task_id: &str,
         value: i64,
         dt: Option<DateTime>,
     ) -> Result<()> {
-        const SQL_T: &'static str = "
+        const SQL_T: &str = "
             UPDATE Task SET
                 last_update = :now
             WHERE task_id = :task_id;
         ";
 
-        const SQL_R: &'static str = "
+        const SQL_R: &str = "
             INSERT INTO TaskTimeRanges (task_id, timestamp, start_or_stop) 
                 VALUES (:task_id, :now, :value);
         ";
 
-        let ns = now_timestamp();
+        let ns = self.clock.now_timestamp();
 
         let tx = self.connection.transaction()?;
 
@@ -372,7 +1519,7 @@ impl Database {
         }
 
         if task_updated != 1 {
-            return Err(Error::RangesUpdateError);
+            return Err(Error::RangesUpdate);
         }
 
         tx.commit()?;
@@ -380,30 +1527,115 @@ impl Database {
         Ok(())
     }
 
+    /// Starts time range of the task, stopping the current task.
     pub fn activate_task(&mut self, task_id: &str) -> Result<ActivationStatus> {
         let current_task = self.get_current_task_id()?;
         if let Some(c_task_id) = current_task.as_ref() {
             if c_task_id == task_id {
                 return Ok(ActivationStatus::AlreadyActive);
             } else {
-                self.update_time_ranges(&c_task_id, STOP_VALUE, None)?;
+                self.update_time_ranges(c_task_id, STOP_VALUE, None)?;
             }
         }
         self.update_time_ranges(task_id, START_VALUE, None)?;
         if let Some(c_task_id) = current_task {
-            return Ok(ActivationStatus::Deactivated(c_task_id));
+            Ok(ActivationStatus::Deactivated(c_task_id))
         } else {
             Ok(ActivationStatus::Activated)
         }
     }
 
+    /// Returns all time range records ordered by time, stops before starts of the same time.
+    pub fn get_range_events(&self) -> Result<Vec<RangeEvent>> {
+        const SQL: &str = "
+            SELECT rowid, task_id, timestamp, start_or_stop FROM TaskTimeRanges
+                ORDER BY timestamp, start_or_stop, rowid;
+        ";
+        let mut stmt = self.connection.prepare(SQL)?;
+        let result: rusqlite::Result<Vec<RangeEvent>> = stmt
+            .query_map((), |r| {
+                Ok(RangeEvent {
+                    rowid: r.get(0)?,
+                    task_id: r.get(1)?,
+                    timestamp: from_timestamp(r.get(2)?),
+                    start_or_stop: r.get(3)?,
+                })
+            })?
+            .collect();
+        result.map_err(|e| e.into())
+    }
+
+    /// Applies changes of time range records in one transaction.
+    /// Tasks getting new records are updated like by [`Database::update_time_ranges`].
+    pub fn apply_range_fixes(&mut self, fixes: &[RangeFix]) -> Result<()> {
+        const SQL_DELETE: &str = "
+            DELETE FROM TaskTimeRanges WHERE rowid = :rowid;
+        ";
+        const SQL_MOVE: &str = "
+            UPDATE TaskTimeRanges SET timestamp = :timestamp WHERE rowid = :rowid;
+        ";
+        const SQL_INSERT: &str = "
+            INSERT INTO TaskTimeRanges (task_id, timestamp, start_or_stop)
+                VALUES (:task_id, :timestamp, :value);
+        ";
+        const SQL_TASK: &str = "
+            INSERT INTO Task (task_id, last_update) VALUES (:task_id, :now);
+        ";
+        const SQL_TOUCH: &str = "
+            UPDATE Task SET last_update = :now WHERE task_id = :task_id;
+        ";
+
+        let tx = self.connection.transaction()?;
+        for fix in fixes {
+            match fix {
+                RangeFix::Delete(rowid) => {
+                    tx.execute(SQL_DELETE, named_params! {":rowid": rowid})?;
+                }
+                RangeFix::Move(rowid, dt) => {
+                    tx.execute(
+                        SQL_MOVE,
+                        named_params! {":rowid": rowid, ":timestamp": to_timestamp(dt)},
+                    )?;
+                }
+                RangeFix::Insert {
+                    task_id,
+                    timestamp,
+                    start_or_stop,
+                } => {
+                    tx.execute(
+                        SQL_INSERT,
+                        named_params! {
+                            ":task_id": task_id,
+                            ":timestamp": to_timestamp(timestamp),
+                            ":value": start_or_stop,
+                        },
+                    )?;
+                    tx.execute(
+                        SQL_TOUCH,
+                        named_params! {":task_id": task_id, ":now": self.clock.now_timestamp()},
+                    )?;
+                }
+                RangeFix::CreateTask(task_id) => {
+                    tx.execute(
+                        SQL_TASK,
+                        named_params! {":task_id": task_id, ":now": self.clock.now_timestamp()},
+                    )?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Returns time ranges per task built from records of the task within the period.
+    /// All filters are optional.
     pub fn select_time_ranges(
         &self,
         task_id: Option<&str>,
         start_date: Option<DateTime>,
         end_date: Option<DateTime>,
     ) -> Result<HashMap<String, Vec<TimeRange>>> {
-        const SQL_BASE: &'static str = "
+        const SQL_BASE: &str = "
             SELECT task_id, timestamp, start_or_stop FROM TaskTimeRanges
         ";
         let where_block = if task_id.is_some() || start_date.is_some() || end_date.is_some() {
@@ -424,7 +1656,10 @@ impl Database {
             "".to_owned()
         };
 
-        let sql = format!("{}{};", SQL_BASE, where_block);
+        let sql = format!(
+            "{}{} ORDER BY timestamp, start_or_stop, rowid;",
+            SQL_BASE, where_block
+        );
         let mut stmt = self.connection.prepare(&sql)?;
         let start_date = start_date.map(|dt| to_timestamp(&dt));
         let end_date = end_date.map(|dt| to_timestamp(&dt));
@@ -447,34 +1682,564 @@ impl Database {
             Ok((task_id, timestamp, start_or_stop))
         })?;
 
-        let mut result = HashMap::new();
-
-        for row in row_iter {
-            let (task_id, timestamp, sos): (String, i64, i64) = row?;
-            let datetime = from_timestamp(timestamp);
+        let rows: rusqlite::Result<Vec<(String, i64, i64)>> = row_iter.collect();
+        Ok(collect_time_ranges(rows?.into_iter().map(
+            |(task_id, timestamp, start_or_stop)| {
+                (task_id, from_timestamp(timestamp), start_or_stop)
+            },
+        )))
+    }
+}
 
-            if !result.contains_key(&task_id) {
-                result.insert(task_id.clone(), Vec::new());
+/// Builds time ranges per task from start and stop records ordered by time,
+/// stops before starts of the same time. Stop without preceding start gives range without start.
+pub(crate) fn collect_time_ranges(
+    records: impl Iterator<Item = (String, DateTime, i64)>,
+) -> HashMap<String, Vec<TimeRange>> {
+    let mut result: HashMap<String, Vec<TimeRange>> = HashMap::new();
+    for (task_id, datetime, start_or_stop) in records {
+        let records = result.entry(task_id).or_default();
+        if start_or_stop == START_VALUE {
+            records.push(TimeRange {
+                start: Some(datetime),
+                end: None,
+            });
+        } else {
+            match records.last_mut() {
+                Some(last) => last.end = Some(datetime),
+                None => records.push(TimeRange {
+                    start: None,
+                    end: Some(datetime),
+                }),
             }
+        }
+    }
+    result
+}
 
-            let records = result.get_mut(&task_id).unwrap();
+/// Checks that budget hours are a finite positive number.
+pub fn check_budget(budget: Option<f64>) -> Result<()> {
+    match budget {
+        Some(hours) if !(hours.is_finite() && hours > 0.0) => Err(Error::InvalidBudget),
+        _ => Ok(()),
+    }
+}
 
-            if sos == START_VALUE {
-                records.push(TimeRange {
-                    start: Some(datetime),
-                    end: None,
-                });
-            } else {
-                match records.last_mut() {
-                    Some(last) => last.end = Some(datetime),
-                    None => records.push(TimeRange {
-                        start: None,
-                        end: Some(datetime),
-                    }),
-                }
+/// Checks that number of days is finite and not negative.
+pub fn check_days(days: Option<f64>) -> Result<()> {
+    match days {
+        Some(days) if !(days.is_finite() && days >= 0.0) => Err(Error::InvalidDays),
+        _ => Ok(()),
+    }
+}
+
+/// Stores change of task field in history.
+fn record_change(
+    connection: &Connection,
+    task_id: &str,
+    field: &str,
+    old_value: Option<String>,
+    new_value: Option<String>,
+    now: i64,
+) -> Result<()> {
+    const SQL: &str = "
+        INSERT INTO TaskHistory (task_id, field, old_value, new_value, timestamp)
+            VALUES (:task_id, :field, :old_value, :new_value, :now);
+    ";
+    connection.execute(
+        SQL,
+        named_params! {
+            ":task_id": task_id,
+            ":field": field,
+            ":old_value": old_value,
+            ":new_value": new_value,
+            ":now": now,
+        },
+    )?;
+    Ok(())
+}
+
+fn is_primary_key_error(error: &rusqlite::Error) -> bool {
+    use rusqlite::ffi::{Error, ErrorCode};
+    const PRIMARY_KEY_ERROR: Error = Error {
+        code: ErrorCode::ConstraintViolation,
+        extended_code: 1555,
+    };
+    matches!(error, rusqlite::Error::SqliteFailure(PRIMARY_KEY_ERROR, _))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AbsenceKind, ActivationStatus, Database, DeleteStatus, MergeStatus, RenameStatus,
+        TaskStatus, START_VALUE, STOP_VALUE,
+    };
+    use crate::error::Error;
+    use crate::report::build_task_reports;
+    use crate::storage::{MemoryStorage, Storage};
+    use crate::time_ranges::{Clock, DateTime, FixedClock};
+    use chrono::{Duration, TimeZone};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn memory_database() -> Database {
+        let now = chrono::Utc.ymd(2023, 3, 31).and_hms(18, 0, 0);
+        Database::open_path(":memory:", Box::new(FixedClock(now))).unwrap()
+    }
+
+    /// Clock showing time set by the test.
+    struct SharedClock(Rc<Cell<DateTime>>);
+
+    impl Clock for SharedClock {
+        fn now(&self) -> DateTime {
+            self.0.get()
+        }
+    }
+
+    #[test]
+    fn open_path_test() {
+        let db = memory_database();
+        db.new_task("A", None, Some("Title"), None, None, None)
+            .unwrap();
+        let task = db.get_task("A").unwrap().unwrap();
+        assert_eq!(task.title.as_deref(), Some("Title"));
+        assert!(db.get_task("B").unwrap().is_none());
+    }
+
+    #[test]
+    fn activate_same_time_test() {
+        let mut db = memory_database();
+        db.new_task("A", None, None, None, None, None).unwrap();
+        db.new_task("B", None, None, None, None, None).unwrap();
+        assert!(matches!(
+            db.activate_task("A").unwrap(),
+            ActivationStatus::Activated
+        ));
+        assert_eq!(db.get_current_task_id().unwrap().as_deref(), Some("A"));
+        assert!(matches!(
+            db.activate_task("B").unwrap(),
+            ActivationStatus::Deactivated(t) if t == "A"
+        ));
+        assert_eq!(db.get_current_task_id().unwrap().as_deref(), Some("B"));
+        assert!(matches!(
+            db.activate_task("B").unwrap(),
+            ActivationStatus::AlreadyActive
+        ));
+        db.update_time_ranges("B", STOP_VALUE, None).unwrap();
+        assert_eq!(db.get_current_task_id().unwrap(), None);
+    }
+
+    #[test]
+    fn task_exists_test() {
+        fn check(storage: &impl Storage) {
+            storage.new_task("A", None, None, None, None, None).unwrap();
+            assert!(matches!(
+                storage.new_task("A", None, Some("Other"), None, None, None),
+                Err(Error::TaskExists)
+            ));
+        }
+        check(&memory_database());
+        check(&MemoryStorage::new());
+    }
+
+    #[test]
+    fn task_status_test() {
+        let now = Rc::new(Cell::new(chrono::Utc.ymd(2023, 3, 31).and_hms(9, 0, 0)));
+        let mut db = Database::open_path(":memory:", Box::new(SharedClock(now.clone()))).unwrap();
+        for task_id in ["A", "B", "C"] {
+            db.new_task(task_id, None, None, None, None, None).unwrap();
+        }
+        db.activate_task("A").unwrap();
+        now.set(now.get() + Duration::hours(1));
+        assert_eq!(
+            db.set_task_status("A", TaskStatus::Done).unwrap(),
+            Some(TaskStatus::Open)
+        );
+        assert_eq!(db.get_current_task_id().unwrap(), None);
+        now.set(now.get() + Duration::minutes(1));
+        assert_eq!(
+            db.set_task_status("B", TaskStatus::Archived).unwrap(),
+            Some(TaskStatus::Open)
+        );
+        assert_eq!(db.set_task_status("D", TaskStatus::Done).unwrap(), None);
+
+        let ids = |tasks: Vec<super::Task>| -> Vec<String> {
+            tasks.into_iter().map(|t| t.task_id).collect()
+        };
+        assert_eq!(ids(db.list_tasks(None, false).unwrap()), ["A", "C"]);
+        assert_eq!(ids(db.list_tasks(None, true).unwrap()), ["B", "A", "C"]);
+        assert_eq!(ids(db.list_tasks(Some(1), true).unwrap()), ["B"]);
+
+        now.set(now.get() + Duration::minutes(1));
+        db.set_task_status("B", TaskStatus::Open).unwrap();
+        assert_eq!(ids(db.list_tasks(None, false).unwrap()), ["B", "A", "C"]);
+        let ranges = db.select_time_ranges(Some("A"), None, None).unwrap();
+        assert_eq!(
+            ranges["A"][0].end,
+            Some(chrono::Utc.ymd(2023, 3, 31).and_hms(10, 0, 0))
+        );
+    }
+
+    #[test]
+    fn delete_rename_test() {
+        let now = Rc::new(Cell::new(chrono::Utc.ymd(2023, 3, 31).and_hms(9, 0, 0)));
+        let mut db = Database::open_path(":memory:", Box::new(SharedClock(now.clone()))).unwrap();
+        db.new_task("A", None, None, None, None, None).unwrap();
+        db.new_task("B", None, None, None, None, None).unwrap();
+        db.activate_task("A").unwrap();
+        db.add_note("A", "note", None).unwrap();
+        now.set(now.get() + Duration::hours(1));
+        db.update_time_ranges("A", STOP_VALUE, None).unwrap();
+
+        assert!(matches!(
+            db.rename_task("A", "B").unwrap(),
+            RenameStatus::AlreadyExists
+        ));
+        assert!(matches!(
+            db.rename_task("X", "Y").unwrap(),
+            RenameStatus::NotFound
+        ));
+        // Ranges and notes reference the task, the check is deferred till commit.
+        assert!(matches!(
+            db.rename_task("A", "C").unwrap(),
+            RenameStatus::Renamed
+        ));
+        assert!(db.get_task("A").unwrap().is_none());
+        let ranges = db.select_time_ranges(None, None, None).unwrap();
+        assert_eq!(ranges.keys().collect::<Vec<_>>(), ["C"]);
+        assert_eq!(db.get_notes(Some("C"), None, None).unwrap().len(), 1);
+
+        assert!(matches!(
+            db.delete_task("C", false).unwrap(),
+            DeleteStatus::HasRanges(2)
+        ));
+        assert!(db.get_task("C").unwrap().is_some());
+        assert!(matches!(
+            db.delete_task("C", true).unwrap(),
+            DeleteStatus::Deleted
+        ));
+        assert!(db.get_task("C").unwrap().is_none());
+        assert!(db.select_time_ranges(None, None, None).unwrap().is_empty());
+        assert!(db.get_notes(None, None, None).unwrap().is_empty());
+        assert!(matches!(
+            db.delete_task("B", false).unwrap(),
+            DeleteStatus::Deleted
+        ));
+        assert!(matches!(
+            db.delete_task("B", true).unwrap(),
+            DeleteStatus::NotFound
+        ));
+    }
+
+    #[test]
+    fn notes_range_test() {
+        fn check(storage: &impl Storage, now: &Cell<DateTime>) {
+            let day = |d| chrono::Utc.ymd(2023, 3, d).and_hms(12, 0, 0);
+            storage.new_task("A", None, None, None, None, None).unwrap();
+            storage.new_task("B", None, None, None, None, None).unwrap();
+            for (d, task_id, text) in [(3, "A", "first"), (2, "B", "second"), (4, "A", "third")] {
+                now.set(day(d));
+                storage.add_note(task_id, text, Some(day(1))).unwrap();
             }
+            let texts = |task_id, start, end| -> Vec<String> {
+                storage
+                    .get_notes(task_id, start, end)
+                    .unwrap()
+                    .into_iter()
+                    .map(|n| n.text)
+                    .collect()
+            };
+            assert_eq!(texts(None, None, None), ["second", "first", "third"]);
+            assert_eq!(texts(Some("A"), None, None), ["first", "third"]);
+            assert_eq!(texts(None, Some(day(3)), None), ["first", "third"]);
+            assert_eq!(texts(None, None, Some(day(3))), ["second", "first"]);
+            assert_eq!(texts(Some("B"), Some(day(3)), Some(day(4))).len(), 0);
+            let notes = storage.get_notes(Some("B"), None, None).unwrap();
+            assert_eq!(notes[0].timestamp, day(2));
+            assert_eq!(notes[0].range_start, Some(day(1)));
+        }
+        let now = Rc::new(Cell::new(chrono::Utc.ymd(2023, 3, 1).and_hms(0, 0, 0)));
+        let db = Database::open_path(":memory:", Box::new(SharedClock(now.clone()))).unwrap();
+        check(&db, &now);
+        check(
+            &MemoryStorage::with_clock(Box::new(SharedClock(now.clone()))),
+            &now,
+        );
+    }
+
+    #[test]
+    fn vacations_overlap_test() {
+        let at = |m, d, h, min| chrono::Utc.ymd(2023, m, d).and_hms(h, min, 0);
+        fn check(storage: &impl Storage, at: impl Fn(u32, u32, u32, u32) -> DateTime) {
+            storage
+                .add_vacation(at(3, 30, 0, 0), at(4, 3, 23, 59), AbsenceKind::Vacation)
+                .unwrap();
+            storage
+                .add_vacation(at(4, 10, 0, 0), at(4, 10, 23, 59), AbsenceKind::Sick)
+                .unwrap();
+            assert!(matches!(
+                storage.add_vacation(at(4, 12, 0, 0), at(4, 11, 23, 59), AbsenceKind::Vacation),
+                Err(Error::InvalidRange)
+            ));
+            let count = |start, end, kind| storage.list_vacations(start, end, kind).unwrap().len();
+            // Vacation crossing bound of the month is listed in both months.
+            assert_eq!(count(at(3, 1, 0, 0), at(3, 31, 23, 59), None), 1);
+            assert_eq!(count(at(4, 1, 0, 0), at(4, 30, 23, 59), None), 2);
+            assert_eq!(
+                count(at(4, 1, 0, 0), at(4, 30, 23, 59), Some(AbsenceKind::Sick)),
+                1
+            );
+            assert_eq!(count(at(4, 3, 23, 59), at(4, 10, 0, 0), None), 2);
+            assert_eq!(count(at(4, 4, 0, 0), at(4, 9, 23, 59), None), 0);
         }
+        check(&memory_database(), at);
+        check(&MemoryStorage::new(), at);
 
-        Ok(result)
+        let db = memory_database();
+        db.add_vacation(at(3, 6, 0, 0), at(3, 7, 23, 59), AbsenceKind::Vacation)
+            .unwrap();
+        db.add_vacation(at(3, 9, 0, 0), at(3, 10, 23, 59), AbsenceKind::Vacation)
+            .unwrap();
+        let ids: Vec<i64> = db
+            .list_vacations(at(3, 7, 0, 0), at(3, 9, 23, 59), None)
+            .unwrap()
+            .iter()
+            .map(|v| v.vacation_id)
+            .collect();
+        assert_eq!(ids.len(), 2);
+        assert!(matches!(
+            db.replace_vacations(&ids, at(3, 10, 0, 0), at(3, 6, 0, 0), AbsenceKind::Vacation),
+            Err(Error::InvalidRange)
+        ));
+        assert_eq!(db.get_vacations().unwrap().len(), 2);
+        db.replace_vacations(
+            &ids,
+            at(3, 6, 0, 0),
+            at(3, 10, 23, 59),
+            AbsenceKind::Vacation,
+        )
+        .unwrap();
+        assert_eq!(
+            db.get_vacations().unwrap(),
+            [(at(3, 6, 0, 0), at(3, 10, 23, 59))]
+        );
+    }
+
+    #[test]
+    fn merge_open_range_test() {
+        let dt = |h| chrono::Utc.ymd(2023, 3, 31).and_hms(h, 0, 0);
+        let mut db = memory_database();
+        db.new_task("A", None, None, None, None, None).unwrap();
+        db.new_task("B", None, None, None, None, None).unwrap();
+        db.update_time_ranges("A", START_VALUE, Some(dt(9)))
+            .unwrap();
+        db.update_time_ranges("B", START_VALUE, Some(dt(10)))
+            .unwrap();
+        db.update_time_ranges("B", STOP_VALUE, Some(dt(11)))
+            .unwrap();
+        // Range of A is never stopped and would absorb the range of B.
+        for (source, target) in [("A", "B"), ("B", "A")] {
+            assert!(matches!(
+                db.merge_tasks(source, target).unwrap(),
+                MergeStatus::OpenRange(t) if t == "A"
+            ));
+        }
+        assert_eq!(db.select_time_ranges(None, None, None).unwrap().len(), 2);
+
+        // Open range of the current task is merged as is.
+        db.update_time_ranges("A", STOP_VALUE, Some(dt(10)))
+            .unwrap();
+        db.update_time_ranges("A", START_VALUE, Some(dt(12)))
+            .unwrap();
+        assert!(matches!(
+            db.merge_tasks("A", "B").unwrap(),
+            MergeStatus::Merged
+        ));
+        assert_eq!(db.get_current_task_id().unwrap().as_deref(), Some("B"));
+        let ranges = db.select_time_ranges(None, None, None).unwrap();
+        assert_eq!(ranges["B"].len(), 2);
+        assert_eq!(ranges["B"][0].end, Some(dt(11)));
+        assert_eq!(ranges["B"][1].start, Some(dt(12)));
+        assert_eq!(ranges["B"][1].end, None);
+    }
+
+    #[test]
+    fn invalid_budget_test() {
+        let db = memory_database();
+        for budget in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                db.new_task("A", None, None, None, None, Some(budget)),
+                Err(Error::InvalidBudget)
+            ));
+            assert!(matches!(
+                db.set_workpackage_budget("WP", Some(budget)),
+                Err(Error::InvalidBudget)
+            ));
+        }
+        db.new_task("A", None, None, None, None, Some(10.0))
+            .unwrap();
+        assert!(matches!(
+            db.update_task(
+                "A",
+                None,
+                None,
+                None,
+                None,
+                Some(-5.0),
+                false,
+                false,
+                false,
+                false,
+                false
+            ),
+            Err(Error::InvalidBudget)
+        ));
+        assert_eq!(db.get_task("A").unwrap().unwrap().budget, Some(10.0));
+
+        for days in [-1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                db.set_vacation_entitlement(2023, days, None),
+                Err(Error::InvalidDays)
+            ));
+            assert!(matches!(
+                db.set_vacation_entitlement(2023, 20.0, Some(days)),
+                Err(Error::InvalidDays)
+            ));
+        }
+        db.set_vacation_entitlement(2023, 0.0, Some(0.0)).unwrap();
+    }
+
+    #[test]
+    fn undo_redo_test() {
+        let mut db = memory_database();
+        assert!(db.undo().unwrap().is_none());
+
+        db.begin_operation("new A").unwrap();
+        db.new_task("A", None, Some("Title"), None, None, None)
+            .unwrap();
+        db.finish_operation().unwrap();
+        db.begin_operation("edit A").unwrap();
+        db.update_task(
+            "A",
+            None,
+            Some("Changed"),
+            None,
+            None,
+            None,
+            false,
+            false,
+            false,
+            false,
+            false,
+        )
+        .unwrap();
+        db.finish_operation().unwrap();
+        db.begin_operation("show A").unwrap();
+        db.get_task("A").unwrap();
+        db.finish_operation().unwrap();
+
+        let title = |db: &Database| db.get_task("A").unwrap().and_then(|t| t.title);
+        assert_eq!(db.undo().unwrap().unwrap().description, "edit A");
+        assert_eq!(title(&db).as_deref(), Some("Title"));
+        assert_eq!(db.undo().unwrap().unwrap().description, "new A");
+        assert!(db.get_task("A").unwrap().is_none());
+        assert!(db.undo().unwrap().is_none());
+
+        assert_eq!(db.redo().unwrap().unwrap().description, "new A");
+        assert_eq!(title(&db).as_deref(), Some("Title"));
+
+        // New change drops operations left to redo.
+        db.begin_operation("rename title").unwrap();
+        db.update_task(
+            "A",
+            None,
+            Some("Other"),
+            None,
+            None,
+            None,
+            false,
+            false,
+            false,
+            false,
+            false,
+        )
+        .unwrap();
+        db.finish_operation().unwrap();
+        assert!(db.redo().unwrap().is_none());
+        assert_eq!(db.undo().unwrap().unwrap().description, "rename title");
+        assert_eq!(title(&db).as_deref(), Some("Title"));
+    }
+
+    #[test]
+    fn merge_workpackage_history_test() {
+        let dt = |d, h| chrono::Utc.ymd(2023, 3, d).and_hms(h, 0, 0);
+        let now = Rc::new(Cell::new(dt(1, 8)));
+        let mut db = Database::open_path(":memory:", Box::new(SharedClock(now.clone()))).unwrap();
+        db.new_task("A", None, None, Some("WP1"), None, None)
+            .unwrap();
+        db.new_task("B", None, None, Some("WP2"), None, None)
+            .unwrap();
+        let ranges = [
+            ("A", dt(6, 9), dt(6, 11)),
+            ("B", dt(6, 13), dt(6, 14)),
+            ("A", dt(13, 9), dt(13, 12)),
+        ];
+        for (task_id, start, end) in ranges {
+            db.update_time_ranges(task_id, START_VALUE, Some(start))
+                .unwrap();
+            db.update_time_ranges(task_id, STOP_VALUE, Some(end))
+                .unwrap();
+        }
+        let set_workpackage = |db: &Database, task_id, wp| {
+            db.update_task(
+                task_id,
+                None,
+                None,
+                Some(wp),
+                None,
+                None,
+                false,
+                false,
+                false,
+                false,
+                false,
+            )
+            .unwrap()
+        };
+        now.set(dt(10, 8));
+        set_workpackage(&db, "A", "WP3");
+        now.set(dt(15, 8));
+        db.rename_task("A", "C").unwrap();
+        now.set(dt(16, 8));
+        set_workpackage(&db, "B", "WP4");
+
+        let calendar = db.work_calendar().unwrap();
+        let workpackage_hours = |db: &Database| -> Vec<(String, Option<String>, Duration)> {
+            let reports =
+                build_task_reports(db, dt(1, 0), dt(31, 23), true, &calendar, None).unwrap();
+            reports
+                .into_iter()
+                .map(|r| (r.task_id, r.workpackage.unwrap(), r.total_hours.raw))
+                .collect()
+        };
+        let wp = |name: &str| Some(name.to_owned());
+        assert_eq!(
+            workpackage_hours(&db),
+            vec![
+                ("B".to_owned(), wp("WP2"), Duration::hours(1)),
+                ("C".to_owned(), wp("WP1"), Duration::hours(2)),
+                ("C".to_owned(), wp("WP3"), Duration::hours(3)),
+            ]
+        );
+
+        now.set(dt(20, 8));
+        db.merge_tasks("B", "C").unwrap();
+        assert_eq!(
+            workpackage_hours(&db),
+            vec![
+                ("C".to_owned(), wp("WP1"), Duration::hours(3)),
+                ("C".to_owned(), wp("WP3"), Duration::hours(3)),
+            ]
+        );
     }
 }
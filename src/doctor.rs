@@ -0,0 +1,600 @@
+//! Detection and fixes of inconsistent time range records.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::database::{RangeEvent, RangeFix, START_VALUE, STOP_VALUE};
+use crate::error::Error;
+use crate::time_ranges::DateTime;
+
+/// Problem found in time range records with changes fixing it.
+#[derive(Debug)]
+pub struct Issue {
+    /// Human readable problem.
+    pub description: String,
+    /// Changes fixing the problem, applied together.
+    pub fixes: Vec<RangeFix>,
+}
+
+/// Closed or still open range built from records.
+#[derive(Debug)]
+pub struct Range<'a> {
+    /// Start record of the range.
+    pub start: &'a RangeEvent,
+    /// Stop record, None if the range is not stopped.
+    pub stop: Option<&'a RangeEvent>,
+}
+
+impl<'a> Range<'a> {
+    fn end(&self) -> Option<DateTime> {
+        self.stop.map(|e| e.timestamp)
+    }
+
+    /// Human readable task and bounds of the range.
+    pub fn describe(&self) -> String {
+        format!(
+            "Task {} range {} - {}",
+            self.start.task_id,
+            format_time(&self.start.timestamp),
+            self.end()
+                .map(|end| format_time(&end))
+                .unwrap_or_else(|| "now".to_owned())
+        )
+    }
+}
+
+/// How to add a range overlapping already tracked ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlapPolicy {
+    /// Add nothing if the range overlaps.
+    Abort,
+    /// Add only parts not covered by other ranges.
+    Split,
+    /// Cut the range out of other ranges.
+    Replace,
+}
+
+fn format_time(dt: &DateTime) -> String {
+    dt.format("%d.%m.%Y %H:%M:%S").to_string()
+}
+
+fn stop_fix(task_id: &str, timestamp: DateTime) -> RangeFix {
+    RangeFix::Insert {
+        task_id: task_id.to_owned(),
+        timestamp,
+        start_or_stop: STOP_VALUE,
+    }
+}
+
+fn start_fix(task_id: &str, timestamp: DateTime) -> RangeFix {
+    RangeFix::Insert {
+        task_id: task_id.to_owned(),
+        timestamp,
+        start_or_stop: START_VALUE,
+    }
+}
+
+/// Checks time range records ordered by time, stops before starts of the same time.
+/// Detects records of missing tasks, records in the future, unmatched starts and stops
+/// and overlapping ranges of different tasks.
+pub fn diagnose(events: &[RangeEvent], tasks: &HashSet<String>, now: DateTime) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    let mut missing: Vec<&String> = events
+        .iter()
+        .map(|e| &e.task_id)
+        .filter(|t| !tasks.contains(*t))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    missing.sort();
+    for task_id in missing {
+        issues.push(Issue {
+            description: format!("Time ranges reference missing task {}", task_id),
+            fixes: vec![RangeFix::CreateTask(task_id.clone())],
+        });
+    }
+
+    let (future, events): (Vec<&RangeEvent>, Vec<&RangeEvent>) =
+        events.iter().partition(|e| e.timestamp > now);
+    for event in future {
+        issues.push(Issue {
+            description: format!(
+                "Task {} has {} record in the future at {}",
+                event.task_id,
+                if event.start_or_stop == START_VALUE {
+                    "start"
+                } else {
+                    "stop"
+                },
+                format_time(&event.timestamp)
+            ),
+            fixes: vec![RangeFix::Delete(event.rowid)],
+        });
+    }
+
+    // Time of the record following the given one, any task is taken into account.
+    // Stop inserted at this time is ordered before the following record if it is a start.
+    let next_time = |event: &RangeEvent| {
+        events
+            .iter()
+            .skip_while(|e| e.rowid != event.rowid)
+            .nth(1)
+            .map(|e| e.timestamp)
+    };
+    let last_event = events.last().map(|e| e.rowid);
+
+    let mut by_task: HashMap<&str, Vec<&RangeEvent>> = HashMap::new();
+    for event in events.iter() {
+        by_task.entry(&event.task_id).or_default().push(event);
+    }
+    let mut task_ids: Vec<&str> = by_task.keys().copied().collect();
+    task_ids.sort();
+
+    let mut ranges: Vec<Range> = Vec::new();
+    for task_id in task_ids {
+        let mut open: Option<&RangeEvent> = None;
+        for event in by_task[task_id].iter() {
+            if event.start_or_stop == START_VALUE {
+                if let Some(start) = open {
+                    issues.push(Issue {
+                        description: format!(
+                            "Task {} started at {} is started again at {} without stop",
+                            task_id,
+                            format_time(&start.timestamp),
+                            format_time(&event.timestamp)
+                        ),
+                        fixes: vec![stop_fix(
+                            task_id,
+                            next_time(start).unwrap_or(event.timestamp),
+                        )],
+                    });
+                }
+                open = Some(event);
+            } else {
+                match open.take() {
+                    Some(start) => ranges.push(Range {
+                        start,
+                        stop: Some(event),
+                    }),
+                    None => issues.push(Issue {
+                        description: format!(
+                            "Task {} is stopped at {} without start",
+                            task_id,
+                            format_time(&event.timestamp)
+                        ),
+                        fixes: vec![RangeFix::Delete(event.rowid)],
+                    }),
+                }
+            }
+        }
+        if let Some(start) = open {
+            if Some(start.rowid) == last_event {
+                ranges.push(Range { start, stop: None });
+            } else {
+                issues.push(Issue {
+                    description: format!(
+                        "Task {} started at {} is never stopped",
+                        task_id,
+                        format_time(&start.timestamp)
+                    ),
+                    fixes: vec![stop_fix(task_id, next_time(start).unwrap())],
+                });
+            }
+        }
+    }
+
+    ranges.sort_by_key(|r| (r.start.timestamp, r.start.rowid));
+    let mut latest: Option<&Range> = None;
+    for range in ranges.iter() {
+        if let Some(prev) = latest {
+            let prev_end = prev.stop.map(|e| e.timestamp);
+            if prev_end
+                .map(|end| end > range.start.timestamp)
+                .unwrap_or(true)
+            {
+                let task_id = &prev.start.task_id;
+                let mut fixes = Vec::new();
+                match prev.stop {
+                    Some(stop) => fixes.push(RangeFix::Move(stop.rowid, range.start.timestamp)),
+                    None => fixes.push(stop_fix(task_id, range.start.timestamp)),
+                }
+                let range_end = range.stop.map(|e| e.timestamp);
+                let continues = match (prev_end, range_end) {
+                    (_, None) => false,
+                    (None, Some(_)) => true,
+                    (Some(prev_end), Some(end)) => prev_end > end,
+                };
+                if continues {
+                    fixes.push(start_fix(task_id, range_end.unwrap()));
+                    if let Some(prev_end) = prev_end {
+                        fixes.push(stop_fix(task_id, prev_end));
+                    }
+                }
+                issues.push(Issue {
+                    description: format!(
+                        "Task {} range started at {} overlaps with task {} range started at {}",
+                        task_id,
+                        format_time(&prev.start.timestamp),
+                        range.start.task_id,
+                        format_time(&range.start.timestamp)
+                    ),
+                    fixes,
+                });
+            }
+            let range_ends_later = match (prev_end, range.stop) {
+                (None, _) => false,
+                (Some(_), None) => true,
+                (Some(prev_end), Some(stop)) => stop.timestamp > prev_end,
+            };
+            if !range_ends_later {
+                continue;
+            }
+        }
+        latest = Some(range);
+    }
+
+    issues
+}
+
+/// Ranges of all tasks overlapping with the range from start till end, open if end is None.
+/// Starts are paired with the next stop of the same task, unmatched stops are ignored.
+pub fn overlapping_ranges(
+    events: &[RangeEvent],
+    start: DateTime,
+    end: Option<DateTime>,
+) -> Vec<Range<'_>> {
+    let mut open: HashMap<&str, &RangeEvent> = HashMap::new();
+    let mut ranges = Vec::new();
+    for event in events {
+        if event.start_or_stop == START_VALUE {
+            open.entry(&event.task_id).or_insert(event);
+        } else if let Some(start) = open.remove(event.task_id.as_str()) {
+            ranges.push(Range {
+                start,
+                stop: Some(event),
+            });
+        }
+    }
+    ranges.extend(open.into_values().map(|start| Range { start, stop: None }));
+    ranges.retain(|r| {
+        end.map(|end| r.start.timestamp < end).unwrap_or(true)
+            && r.end().map(|e| start < e).unwrap_or(true)
+    });
+    ranges.sort_by_key(|r| (r.start.timestamp, r.start.rowid));
+    ranges
+}
+
+/// Changes adding range of the task from start till end, open if end is None.
+/// Returns overlapping ranges as error if policy is abort or if the overlap can't be resolved.
+pub fn plan_range_insert<'a>(
+    events: &'a [RangeEvent],
+    task_id: &str,
+    start: DateTime,
+    end: Option<DateTime>,
+    policy: OverlapPolicy,
+) -> Result<Vec<RangeFix>, Vec<Range<'a>>> {
+    let overlapping = overlapping_ranges(events, start, end);
+    let end = match end {
+        None if overlapping.is_empty() => return Ok(vec![start_fix(task_id, start)]),
+        None => return Err(overlapping),
+        Some(end) => end,
+    };
+    let mut fixes = Vec::new();
+    match policy {
+        OverlapPolicy::Abort if !overlapping.is_empty() => return Err(overlapping),
+        OverlapPolicy::Abort => {
+            fixes.push(start_fix(task_id, start));
+            fixes.push(stop_fix(task_id, end));
+        }
+        OverlapPolicy::Split => {
+            let mut gap_start = start;
+            for range in overlapping.iter() {
+                if range.start.timestamp > gap_start {
+                    fixes.push(start_fix(task_id, gap_start));
+                    fixes.push(stop_fix(task_id, range.start.timestamp));
+                }
+                gap_start = match range.end() {
+                    None => end,
+                    Some(range_end) => gap_start.max(range_end),
+                };
+            }
+            if gap_start < end {
+                fixes.push(start_fix(task_id, gap_start));
+                fixes.push(stop_fix(task_id, end));
+            }
+        }
+        OverlapPolicy::Replace => {
+            for range in overlapping.iter() {
+                let range_task = &range.start.task_id;
+                let ends_inside = range.end().map(|e| e <= end).unwrap_or(false);
+                if range.start.timestamp < start {
+                    match range.stop {
+                        Some(stop) => fixes.push(RangeFix::Move(stop.rowid, start)),
+                        None => fixes.push(stop_fix(range_task, start)),
+                    }
+                    if !ends_inside {
+                        fixes.push(start_fix(range_task, end));
+                        if let Some(range_end) = range.end() {
+                            fixes.push(stop_fix(range_task, range_end));
+                        }
+                    }
+                } else if ends_inside {
+                    fixes.push(RangeFix::Delete(range.start.rowid));
+                    fixes.push(RangeFix::Delete(range.stop.unwrap().rowid));
+                } else {
+                    fixes.push(RangeFix::Move(range.start.rowid, end));
+                }
+            }
+            fixes.push(start_fix(task_id, start));
+            fixes.push(stop_fix(task_id, end));
+        }
+    }
+    Ok(fixes)
+}
+
+/// Checks bounds of a range added by hand: start before end and both not after `now`.
+pub fn check_range_bounds(
+    start: Option<DateTime>,
+    end: Option<DateTime>,
+    now: DateTime,
+) -> crate::error::Result<()> {
+    if let (Some(start), Some(end)) = (start, end) {
+        if end <= start {
+            return Err(Error::InvalidRange);
+        }
+    }
+    if start.into_iter().chain(end).any(|t| t > now) {
+        return Err(Error::FutureRange);
+    }
+    Ok(())
+}
+
+/// Change stopping the open range of the task at end. Returns None if the last record
+/// is not a start of the task before end.
+pub fn plan_range_stop(events: &[RangeEvent], task_id: &str, end: DateTime) -> Option<RangeFix> {
+    events
+        .last()
+        .filter(|e| e.task_id == task_id && e.start_or_stop == START_VALUE && e.timestamp < end)
+        .map(|_| stop_fix(task_id, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_range_bounds, diagnose, plan_range_insert, plan_range_stop, OverlapPolicy};
+    use crate::database::{Database, RangeEvent, RangeFix, START_VALUE, STOP_VALUE};
+    use crate::error::Error;
+    use crate::time_ranges::{from_timestamp, Clock, DateTime, FixedClock};
+    use chrono::TimeZone;
+    use std::cell::Cell;
+    use std::collections::HashSet;
+
+    fn event(rowid: i64, task_id: &str, hour: i64, start_or_stop: i64) -> RangeEvent {
+        RangeEvent {
+            rowid,
+            task_id: task_id.to_owned(),
+            timestamp: from_timestamp(hour * 3_600_000_000_000),
+            start_or_stop,
+        }
+    }
+
+    #[test]
+    fn diagnose_test() {
+        let tasks: HashSet<String> = ["A", "B"].iter().map(|s| s.to_string()).collect();
+        let now = from_timestamp(100 * 3_600_000_000_000);
+
+        let events = vec![
+            event(1, "A", 1, START_VALUE),
+            event(2, "A", 2, STOP_VALUE),
+            event(3, "B", 3, START_VALUE),
+            event(4, "B", 4, STOP_VALUE),
+            event(5, "B", 5, START_VALUE),
+        ];
+        assert!(diagnose(&events, &tasks, now).is_empty());
+
+        let events = vec![
+            event(1, "A", 1, STOP_VALUE),
+            event(2, "A", 2, START_VALUE),
+            event(3, "B", 3, START_VALUE),
+            event(4, "B", 4, STOP_VALUE),
+            event(5, "A", 6, STOP_VALUE),
+            event(6, "C", 7, START_VALUE),
+            event(7, "C", 200, STOP_VALUE),
+        ];
+        let issues = diagnose(&events, &tasks, now);
+        let fixes: Vec<RangeFix> = issues.into_iter().flat_map(|i| i.fixes).collect();
+        assert_eq!(
+            fixes,
+            vec![
+                RangeFix::CreateTask("C".to_owned()),
+                RangeFix::Delete(7),
+                RangeFix::Delete(1),
+                RangeFix::Move(5, from_timestamp(3 * 3_600_000_000_000)),
+                RangeFix::Insert {
+                    task_id: "A".to_owned(),
+                    timestamp: from_timestamp(4 * 3_600_000_000_000),
+                    start_or_stop: START_VALUE,
+                },
+                RangeFix::Insert {
+                    task_id: "A".to_owned(),
+                    timestamp: from_timestamp(6 * 3_600_000_000_000),
+                    start_or_stop: STOP_VALUE,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn plan_range_insert_test() {
+        let hour = |h: i64| from_timestamp(h * 3_600_000_000_000);
+        let insert = |task_id: &str, h: i64, start_or_stop: i64| RangeFix::Insert {
+            task_id: task_id.to_owned(),
+            timestamp: hour(h),
+            start_or_stop,
+        };
+        let events = vec![
+            event(1, "A", 1, START_VALUE),
+            event(2, "A", 3, STOP_VALUE),
+            event(3, "B", 5, START_VALUE),
+            event(4, "B", 6, STOP_VALUE),
+        ];
+
+        assert_eq!(
+            plan_range_insert(&events, "C", hour(3), Some(hour(5)), OverlapPolicy::Abort).unwrap(),
+            vec![insert("C", 3, START_VALUE), insert("C", 5, STOP_VALUE)]
+        );
+        let conflicts =
+            plan_range_insert(&events, "C", hour(2), Some(hour(8)), OverlapPolicy::Abort)
+                .unwrap_err();
+        assert_eq!(conflicts.len(), 2);
+
+        assert_eq!(
+            plan_range_insert(&events, "C", hour(2), Some(hour(8)), OverlapPolicy::Split).unwrap(),
+            vec![
+                insert("C", 3, START_VALUE),
+                insert("C", 5, STOP_VALUE),
+                insert("C", 6, START_VALUE),
+                insert("C", 8, STOP_VALUE),
+            ]
+        );
+
+        assert_eq!(
+            plan_range_insert(&events, "C", hour(2), Some(hour(8)), OverlapPolicy::Replace)
+                .unwrap(),
+            vec![
+                RangeFix::Move(2, hour(2)),
+                RangeFix::Delete(3),
+                RangeFix::Delete(4),
+                insert("C", 2, START_VALUE),
+                insert("C", 8, STOP_VALUE),
+            ]
+        );
+    }
+
+    #[test]
+    fn fix_started_again_test() {
+        let dt = |h| chrono::Utc.ymd(2023, 3, 7).and_hms(h, 0, 0);
+        let mut db = Database::open_path(":memory:", Box::new(FixedClock(dt(18)))).unwrap();
+        for task_id in ["A", "B"] {
+            db.new_task(task_id, None, None, None, None, None).unwrap();
+        }
+        db.update_time_ranges("A", START_VALUE, Some(dt(9)))
+            .unwrap();
+        db.update_time_ranges("A", START_VALUE, Some(dt(13)))
+            .unwrap();
+        db.update_time_ranges("A", STOP_VALUE, Some(dt(15)))
+            .unwrap();
+        db.update_time_ranges("B", START_VALUE, Some(dt(15)))
+            .unwrap();
+
+        let tasks: HashSet<String> = ["A", "B"].iter().map(|s| s.to_string()).collect();
+        let diagnose_db = |db: &Database| diagnose(&db.get_range_events().unwrap(), &tasks, dt(18));
+        let issues = diagnose_db(&db);
+        assert_eq!(issues.len(), 1);
+        db.apply_range_fixes(&issues[0].fixes).unwrap();
+        assert!(diagnose_db(&db).is_empty());
+
+        let ranges = db.select_time_ranges(Some("A"), None, None).unwrap();
+        let hours = ranges["A"].iter().fold(chrono::Duration::zero(), |sum, r| {
+            sum + (r.end.unwrap() - r.start.unwrap())
+        });
+        assert_eq!(hours, chrono::Duration::hours(6));
+        let bounds: Vec<_> = ranges["A"].iter().map(|r| (r.start, r.end)).collect();
+        assert_eq!(
+            bounds,
+            vec![(Some(dt(9)), Some(dt(13))), (Some(dt(13)), Some(dt(15)))]
+        );
+    }
+
+    /// Clock advancing by a second on every call to order changes in time.
+    struct SteppingClock(Cell<DateTime>);
+
+    impl Clock for SteppingClock {
+        fn now(&self) -> DateTime {
+            let now = self.0.get();
+            self.0.set(now + chrono::Duration::seconds(1));
+            now
+        }
+    }
+
+    #[test]
+    fn add_range_test() {
+        let dt = |h| chrono::Utc.ymd(2023, 3, 7).and_hms(h, 0, 0);
+        let tasks: HashSet<String> = ["A", "B", "C"].iter().map(|s| s.to_string()).collect();
+        let bounds = |db: &Database, task_id| -> Vec<_> {
+            db.select_time_ranges(Some(task_id), None, None).unwrap()[task_id]
+                .iter()
+                .map(|r| (r.start, r.end))
+                .collect()
+        };
+        for policy in [OverlapPolicy::Split, OverlapPolicy::Replace] {
+            let clock = SteppingClock(Cell::new(dt(18)));
+            let mut db = Database::open_path(":memory:", Box::new(clock)).unwrap();
+            for task_id in ["A", "B", "C"] {
+                db.new_task(task_id, None, None, None, None, None).unwrap();
+            }
+            db.update_time_ranges("A", START_VALUE, Some(dt(9)))
+                .unwrap();
+            db.update_time_ranges("A", STOP_VALUE, Some(dt(11)))
+                .unwrap();
+            db.update_time_ranges("B", START_VALUE, Some(dt(13)))
+                .unwrap();
+            db.update_time_ranges("B", STOP_VALUE, Some(dt(14)))
+                .unwrap();
+
+            let events = db.get_range_events().unwrap();
+            let fixes = plan_range_insert(&events, "C", dt(10), Some(dt(16)), policy).unwrap();
+            db.apply_range_fixes(&fixes).unwrap();
+            let events = db.get_range_events().unwrap();
+            assert!(diagnose(&events, &tasks, dt(18)).is_empty());
+            if policy == OverlapPolicy::Split {
+                assert_eq!(
+                    bounds(&db, "C"),
+                    vec![(Some(dt(11)), Some(dt(13))), (Some(dt(14)), Some(dt(16)))]
+                );
+            } else {
+                assert_eq!(bounds(&db, "A"), vec![(Some(dt(9)), Some(dt(10)))]);
+                assert_eq!(bounds(&db, "C"), vec![(Some(dt(10)), Some(dt(16)))]);
+                assert!(!db
+                    .select_time_ranges(None, None, None)
+                    .unwrap()
+                    .contains_key("B"));
+            }
+
+            // Open range started after all others makes the task current.
+            let fixes = plan_range_insert(&events, "A", dt(17), None, policy).unwrap();
+            db.apply_range_fixes(&fixes).unwrap();
+            assert_eq!(db.get_current_task_id().unwrap().as_deref(), Some("A"));
+            assert_eq!(db.list_tasks(Some(1), false).unwrap()[0].task_id, "A");
+
+            let events = db.get_range_events().unwrap();
+            assert!(plan_range_stop(&events, "C", dt(18)).is_none());
+            assert!(plan_range_stop(&events, "A", dt(17)).is_none());
+            let fix = plan_range_stop(&events, "A", dt(18)).unwrap();
+            db.apply_range_fixes(&[fix]).unwrap();
+            assert_eq!(db.get_current_task_id().unwrap(), None);
+            let events = db.get_range_events().unwrap();
+            assert!(diagnose(&events, &tasks, dt(18)).is_empty());
+        }
+
+        let now = dt(18);
+        assert!(check_range_bounds(Some(dt(9)), Some(dt(12)), now).is_ok());
+        assert!(check_range_bounds(Some(dt(9)), None, now).is_ok());
+        assert!(check_range_bounds(None, Some(now), now).is_ok());
+        for (start, end) in [(dt(12), dt(9)), (dt(12), dt(12))] {
+            assert!(matches!(
+                check_range_bounds(Some(start), Some(end), now),
+                Err(Error::InvalidRange)
+            ));
+        }
+        for (start, end) in [
+            (Some(dt(19)), None),
+            (None, Some(dt(19))),
+            (Some(dt(17)), Some(dt(19))),
+        ] {
+            assert!(matches!(
+                check_range_bounds(start, end, now),
+                Err(Error::FutureRange)
+            ));
+        }
+    }
+}
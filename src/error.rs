@@ -1,14 +1,49 @@
+//! Error type of the library.
+
 use thiserror::Error;
 
+/// Errors of database and time range operations.
 #[derive(Error, Debug)]
 pub enum Error {
+    /// Reading or writing local files failed.
     #[error("local system io error")]
     IO(#[from] std::io::Error),
+    /// Database query failed.
     #[error("sqlite error")]
-    SQL(#[from] rusqlite::Error),
+    Sql(#[from] rusqlite::Error),
 
+    /// Time range record could not be added.
     #[error("time ranges logic error")]
-    RangesUpdateError,
+    RangesUpdate,
+
+    /// Range ends before its start.
+    #[error("time range starts after its end")]
+    InvalidRange,
+
+    /// Range starts or ends after the current time.
+    #[error("time range is in the future")]
+    FutureRange,
+
+    /// Task with the id exists already.
+    #[error("task already exists")]
+    TaskExists,
+
+    /// Budget is not a finite positive number.
+    #[error("budget must be a positive number of hours")]
+    InvalidBudget,
+
+    /// Number of days is negative or not finite.
+    #[error("number of days must not be negative")]
+    InvalidDays,
+
+    /// Stored value of the setting with the key can't be used.
+    #[error("invalid value of setting {0}, fix it with config")]
+    InvalidSetting(String),
+
+    /// Template could not be parsed or rendered.
+    #[error("template error")]
+    Template(#[from] tera::Error),
 }
 
+/// Result with library [`enum@Error`].
 pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,36 @@
+//! Task time tracking library.
+//!
+//! [`database::Database`] stores tasks, their time ranges, absences and settings in a local
+//! SQLite database, [`storage::Storage`] abstracts tasks, ranges and absences over it and
+//! an in-memory storage. [`time_ranges`] turns tracked ranges into working hours using a holiday
+//! calendar with absences, [`report`] aggregates them per task and month, [`render`]
+//! writes reports as table, CSV, JSON, Markdown, HTML or Excel workbook and [`template`]
+//! renders them through user templates.
+//!
+//! ```no_run
+//! use tasktrack::{database::Database, report, time_ranges};
+//!
+//! # fn main() -> tasktrack::error::Result<()> {
+//! let db = Database::open()?;
+//! let calendar = db.work_calendar()?;
+//! let till = db.clock().now();
+//! let since = till - chrono::Duration::days(30);
+//! for task in report::build_task_reports(&db, since, till, false, &calendar, None)? {
+//!     println!("{} {:.2}", task.task_id, time_ranges::hours(task.total_hours.normalized));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+#![warn(missing_docs)]
+
+pub mod balance;
+pub mod database;
+pub mod doctor;
+pub mod error;
+pub mod render;
+pub mod report;
+pub mod settings;
+pub mod storage;
+pub mod template;
+pub mod time_ranges;
@@ -1,53 +1,192 @@
 mod cli;
-mod database;
-mod error;
-mod time_ranges;
 
-use num_traits::FromPrimitive;
-use std::collections::HashMap;
+use tasktrack::{
+    balance, database, doctor, error, render, report, settings, template, time_ranges,
+};
 
-use database::{ActivationStatus, Database};
+use database::{
+    AbsenceKind, ActivationStatus, Database, DeleteStatus, MergeStatus, RenameStatus, TaskStatus,
+};
 
 use cli::*;
+use render::Renderer;
 
 use clap::Parser;
 
 type CmdResult = error::Result<i32>;
 
 fn main() -> error::Result<()> {
-    let cli = CLI::parse();
-    let db = Database::open()?;
-
-    let return_code = match cli.command {
-        Command::Current => current_task(&db)?,
-        Command::List(args) => list_tasks(&db, args)?,
-        Command::New(args) => new_task(&db, args)?,
-        Command::Edit(args) => edit_task(&db, args)?,
-        Command::Show(args) => show_task(&db, args)?,
-        Command::Activate(args) => activate_task(db, args)?,
-        Command::Report(args) => report(&db, args)?,
-        Command::AddRange(args) => add_range(db, args)?,
-        Command::VacationAdd(args) => add_vacation(db, args)?,
-        Command::VacationRemove(args) => remove_vacation(db, args)?,
-        Command::VacationList(args) => list_vacations(db, args)?,
+    let cli = Cli::parse();
+    let mut db = match cli.now {
+        None => Database::open()?,
+        Some(now) => Database::open_with_clock(Box::new(time_ranges::FixedClock(now.0)))?,
+    };
+
+    let journaled = is_mutating(&cli.command);
+    if journaled {
+        let description: Vec<String> = std::env::args().skip(1).collect();
+        db.begin_operation(&description.join(" "))?;
+    }
+    let result = run_command(&mut db, cli.command);
+    if journaled {
+        db.finish_operation()?;
+    }
+
+    let return_code = match result {
+        Err(
+            err @ (error::Error::InvalidBudget
+            | error::Error::InvalidDays
+            | error::Error::InvalidSetting(_)),
+        ) => {
+            println!("*** {} ***", err);
+            1
+        }
+        result => result?,
     };
     std::process::exit(return_code);
 }
 
-fn add_vacation(db: Database, args: VacationAddArgs) -> CmdResult {
-    let since = args.since.start_datetime();
-    let till = args.till.end_datetime();
-    db.add_vacation(since, till)?;
+/// Whether the command may change data and is recorded in the journal for undo.
+fn is_mutating(command: &Command) -> bool {
+    match command {
+        Command::Current
+        | Command::List(_)
+        | Command::Show(_)
+        | Command::Report(_)
+        | Command::Balance(_)
+        | Command::VacationList(_)
+        | Command::VacationBalance(_)
+        | Command::Absence(AbsenceCommand::Summary(_))
+        | Command::Undo
+        | Command::Redo => false,
+        Command::Config(args) => args.value.is_some() || args.unset,
+        Command::WorkpackageBudget(args) => args.budget.is_some() || args.drop,
+        Command::Doctor(args) => args.fix || args.interactive,
+        _ => true,
+    }
+}
+
+fn run_command(db: &mut Database, command: Command) -> CmdResult {
+    match command {
+        Command::Current => current_task(db),
+        Command::List(args) => list_tasks(db, args),
+        Command::New(args) => new_task(db, args),
+        Command::Edit(args) => edit_task(db, args),
+        Command::Delete(args) => delete_task(db, args),
+        Command::Rename(args) => rename_task(db, args),
+        Command::Merge(args) => merge_tasks(db, args),
+        Command::Show(args) => show_task(db, args),
+        Command::Note(args) => add_note(db, args),
+        Command::Activate(args) => activate_task(db, args),
+        Command::Close(args) => set_task_status(db, args, TaskStatus::Done),
+        Command::Archive(args) => set_task_status(db, args, TaskStatus::Archived),
+        Command::Reopen(args) => set_task_status(db, args, TaskStatus::Open),
+        Command::Report(args) => report(db, args),
+        Command::Balance(args) => balance(db, args),
+        Command::AddRange(args) => add_range(db, args),
+        Command::VacationAdd(args) => add_absence(db, args, AbsenceKind::Vacation),
+        Command::VacationRemove(args) => remove_vacation(db, args),
+        Command::VacationList(args) => list_vacations(db, args),
+        Command::VacationEntitlement(args) => vacation_entitlement(db, args),
+        Command::VacationBalance(args) => vacation_balance(db, args),
+        Command::Absence(AbsenceCommand::Add(args)) => {
+            add_absence(db, args.range, absence_kind(args.kind))
+        }
+        Command::Absence(AbsenceCommand::Summary(args)) => absence_summary(db, args),
+        Command::WorkpackageBudget(args) => workpackage_budget(db, args),
+        Command::Config(args) => config(db, args),
+        Command::Doctor(args) => doctor(db, args),
+        Command::Undo => undo(db),
+        Command::Redo => redo(db),
+    }
+}
+
+fn absence_kind(kind: CliAbsenceKind) -> AbsenceKind {
+    match kind {
+        CliAbsenceKind::Vacation => AbsenceKind::Vacation,
+        CliAbsenceKind::Sick => AbsenceKind::Sick,
+        CliAbsenceKind::Training => AbsenceKind::Training,
+        CliAbsenceKind::Parental => AbsenceKind::Parental,
+        CliAbsenceKind::Other => AbsenceKind::Other,
+    }
+}
+
+fn add_absence(db: &Database, args: VacationAddArgs, kind: AbsenceKind) -> CmdResult {
+    let (since, till) = match args.half {
+        Some(half) => {
+            if args.since.0 != args.till.0 {
+                println!("*** Half day vacation must start and end on the same date ***");
+                return Ok(1);
+            }
+            let date = args.since.0.naive_utc();
+            match half {
+                HalfDay::Morning => time_ranges::morning(date),
+                HalfDay::Afternoon => time_ranges::afternoon(date),
+            }
+        }
+        None => (
+            args.from
+                .map(|t| args.since.0.and_time(t.0).unwrap())
+                .unwrap_or_else(|| args.since.start_datetime()),
+            args.to
+                .map(|t| args.till.0.and_time(t.0).unwrap())
+                .unwrap_or_else(|| args.till.end_datetime()),
+        ),
+    };
+    if since > till {
+        println!("*** Vacation start is after its end ***");
+        return Ok(1);
+    }
+
+    let overlapping = db.list_vacations(since, till, None)?;
+    if overlapping.is_empty() {
+        db.add_vacation(since, till, kind)?;
+        return Ok(0);
+    }
+    if !args.merge || overlapping.iter().any(|v| v.kind != kind) {
+        println!("*** Vacation overlaps with: ***");
+        for vacation in overlapping.iter() {
+            println!(
+                "\t{} {} - {} {}",
+                vacation.vacation_id,
+                report::format_absence_time(vacation.start, "00:00"),
+                report::format_absence_time(vacation.end, "23:59"),
+                vacation.kind
+            );
+        }
+        if overlapping.iter().any(|v| v.kind != kind) {
+            println!("*** Only vacations of the same kind can be merged ***");
+        } else {
+            println!("*** Use --merge to merge them ***");
+        }
+        return Ok(1);
+    }
+
+    let since = overlapping
+        .iter()
+        .map(|v| v.start)
+        .fold(since, |a, b| a.min(b));
+    let till = overlapping
+        .iter()
+        .map(|v| v.end)
+        .fold(till, |a, b| a.max(b));
+    let ids: Vec<i64> = overlapping.iter().map(|v| v.vacation_id).collect();
+    db.replace_vacations(&ids, since, till, kind)?;
+    println!(
+        "Vacation merged with {} vacations: {} - {}",
+        ids.len(),
+        report::format_absence_time(since, "00:00"),
+        report::format_absence_time(till, "23:59")
+    );
     Ok(0)
 }
 
-fn remove_vacation(db: Database, args: VacationRemoveArgs) -> CmdResult {
+fn remove_vacation(db: &Database, args: VacationRemoveArgs) -> CmdResult {
     db.delete_vacation(args.id)?;
     Ok(0)
 }
 
-fn list_vacations(db: Database, args: VacationListArgs) -> CmdResult {
-    use prettytable::{format::FormatBuilder, Cell, Row, Table};
+fn list_vacations(db: &Database, args: VacationListArgs) -> CmdResult {
     let since = args
         .since
         .map(|d| d.start_datetime())
@@ -55,8 +194,8 @@ fn list_vacations(db: Database, args: VacationListArgs) -> CmdResult {
     let till = args
         .till
         .map(|d| d.end_datetime())
-        .unwrap_or(time_ranges::now());
-    let vacations = db.list_vacations(since, till)?;
+        .unwrap_or(time_ranges::from_timestamp(i64::MAX));
+    let vacations = db.list_vacations(since, till, args.kind.map(absence_kind))?;
     if vacations.is_empty() {
         if args.since.is_some() || args.till.is_some() {
             println!("*** No vacations found in specified period");
@@ -64,182 +203,303 @@ fn list_vacations(db: Database, args: VacationListArgs) -> CmdResult {
             println!("*** No vacations found");
         }
         return Ok(1);
-    } else {
-        let format = FormatBuilder::new()
-            .column_separator(' ')
-            .borders(' ')
-            .padding(0, 0)
-            .build();
+    }
+    let report = report::Report {
+        since,
+        till,
+        tables: vec![report::vacations_table(&vacations)],
+    };
+    render::TableRenderer.render(&report, &mut std::io::stdout())?;
+    Ok(0)
+}
 
-        let mut table = Table::new();
-        table.set_format(format);
+/// Returns absence days per kind between dates inclusively.
+fn absence_summary(db: &Database, args: AbsenceSummaryArgs) -> CmdResult {
+    use chrono::Datelike;
+    let year = args.year.unwrap_or_else(|| db.clock().now().year());
+    let days = report::absence_days_by_kind(
+        db,
+        chrono::NaiveDate::from_ymd(year, 1, 1),
+        chrono::NaiveDate::from_ymd(year, 12, 31),
+    )?;
+    println!("Absences in {}:", year);
+    for kind in AbsenceKind::ALL {
+        println!("\t{}: {:.1} days", kind, days[&kind]);
+    }
+    let balance = balance::vacation_balance(db, year)?;
+    println!(
+        "Vacation days: {:.1} of {:.1} used, {:.1} remaining",
+        balance.used,
+        balance.entitlement + balance.carry_over,
+        balance.remaining()
+    );
+    Ok(0)
+}
 
-        for vacation in vacations {
-            let mut row = Vec::new();
-            row.push(Cell::new(&vacation.0.to_string()));
-            row.push(Cell::new(&vacation.1.date().format("%d.%m.%Y").to_string()));
-            row.push(Cell::new(&vacation.2.date().format("%d.%m.%Y").to_string()));
-            table.add_row(Row::new(row));
-        }
+fn vacation_entitlement(db: &Database, args: VacationEntitlementArgs) -> CmdResult {
+    db.set_vacation_entitlement(args.year, args.days, args.carry_over)?;
+    vacation_balance(
+        db,
+        VacationBalanceArgs {
+            year: Some(args.year),
+        },
+    )
+}
 
-        table.printstd();
-    }
+fn vacation_balance(db: &Database, args: VacationBalanceArgs) -> CmdResult {
+    use chrono::Datelike;
+    let year = args.year.unwrap_or_else(|| db.clock().now().year());
+    let balance = balance::vacation_balance(db, year)?;
+    println!("Vacation balance for {}:", year);
+    println!("\tEntitlement: {:.1} days", balance.entitlement);
+    println!("\tCarry-over: {:.1} days", balance.carry_over);
+    println!("\tUsed: {:.1} days", balance.used);
+    println!("\tRemaining: {:.1} days", balance.remaining());
     Ok(0)
 }
 
-fn add_range(mut db: Database, args: AddRangeArgs) -> CmdResult {
+fn add_range(db: &mut Database, args: AddRangeArgs) -> CmdResult {
     if db.get_task(&args.task_id)?.is_none() {
         println!("*** No task with id {}. ***", args.task_id);
         return Ok(1);
     }
-    if let Some(since) = args.since {
-        db.update_time_ranges(
-            &args.task_id,
-            database::START_VALUE,
-            Some(since.start_datetime()),
-        )?;
-        println!("Add start point to task with id {}.", args.task_id);
+    let since = args.since.map(|s| s.start_datetime());
+    let till = args.till.map(|t| t.end_datetime());
+    if let Err(err) = doctor::check_range_bounds(since, till, db.clock().now()) {
+        println!("*** {} ***", err);
+        return Ok(1);
+    }
+    let since = match (since, till) {
+        (Some(since), _) => since,
+        (None, Some(till)) => {
+            let events = db.get_range_events()?;
+            match doctor::plan_range_stop(&events, &args.task_id, till) {
+                None => {
+                    println!(
+                        "*** Task with id {} has no open range started before {} ***",
+                        args.task_id,
+                        till.format("%d.%m.%Y %H:%M")
+                    );
+                    return Ok(1);
+                }
+                Some(fix) => db.apply_range_fixes(&[fix])?,
+            }
+            println!("Add end point to task with id {}.", args.task_id);
+            return Ok(0);
+        }
+        (None, None) => unreachable!("clap requires since or till"),
+    };
+    let policy = if args.split {
+        doctor::OverlapPolicy::Split
+    } else if args.replace {
+        doctor::OverlapPolicy::Replace
+    } else {
+        doctor::OverlapPolicy::Abort
+    };
+
+    let events = db.get_range_events()?;
+    let fixes = match doctor::plan_range_insert(&events, &args.task_id, since, till, policy) {
+        Ok(fixes) => fixes,
+        Err(overlapping) => {
+            for range in overlapping.iter() {
+                println!("{}", range.describe());
+            }
+            println!("*** Range overlaps with tracked ranges, use --split or --replace ***");
+            return Ok(1);
+        }
+    };
+    if fixes.is_empty() {
+        println!("Range is fully covered by other ranges, nothing added.");
+        return Ok(0);
     }
-    if let Some(till) = args.till {
-        db.update_time_ranges(
-            &args.task_id,
-            database::STOP_VALUE,
-            Some(till.end_datetime()),
-        )?;
-        println!("Add end point to task with id {}.", args.task_id);
+    db.apply_range_fixes(&fixes)?;
+    if till.is_some() {
+        println!("Add range to task with id {}.", args.task_id);
+    } else {
+        println!("Add start point to task with id {}.", args.task_id);
     }
     Ok(0)
 }
 
-struct TaskReport {
-    task_id: String,
-    total_hours: f64,
-    month_range: (chrono::Month, chrono::Month),
-    month_hours: HashMap<chrono::Month, f64>,
+fn hours_kind(kind: HoursKind) -> report::HoursKind {
+    match kind {
+        HoursKind::Raw => report::HoursKind::Raw,
+        HoursKind::Normalized => report::HoursKind::Normalized,
+        HoursKind::Both => report::HoursKind::Both,
+    }
+}
+
+/// Rounding rule of the settings overridden by report arguments.
+fn rounding(db: &Database, args: &ReportArgs) -> error::Result<Option<time_ranges::Rounding>> {
+    let increment_minutes = match args.round {
+        Some(minutes) => minutes,
+        None => settings::get_parsed(db, settings::ROUNDING_INCREMENT, |v| v.parse().ok())?,
+    };
+    if increment_minutes == 0 {
+        return Ok(None);
+    }
+    let mode = match args.round_mode {
+        Some(RoundingMode::Up) => time_ranges::RoundingMode::Up,
+        Some(RoundingMode::Down) => time_ranges::RoundingMode::Down,
+        Some(RoundingMode::Nearest) => time_ranges::RoundingMode::Nearest,
+        None => settings::get_parsed(db, settings::ROUNDING_MODE, |v| {
+            time_ranges::RoundingMode::ALL
+                .into_iter()
+                .find(|m| m.as_str() == v)
+        })?,
+    };
+    let scope = match args.round_scope {
+        Some(RoundingScope::Range) => time_ranges::RoundingScope::Range,
+        Some(RoundingScope::Day) => time_ranges::RoundingScope::Day,
+        Some(RoundingScope::Task) => time_ranges::RoundingScope::Task,
+        None => settings::get_parsed(db, settings::ROUNDING_SCOPE, |v| {
+            time_ranges::RoundingScope::ALL
+                .into_iter()
+                .find(|s| s.as_str() == v)
+        })?,
+    };
+    Ok(Some(time_ranges::Rounding {
+        increment_minutes,
+        mode,
+        scope,
+    }))
 }
 
 fn report(db: &Database, args: ReportArgs) -> CmdResult {
-    let since = args.since.start_datetime();
-    let till = args.till.end_datetime();
-    let ranges = db.select_time_ranges(None, Some(since), Some(till))?;
-    let mut reports = Vec::new();
-    let calendar = get_calendar(db)?;
-    for (task_id, task_ranges) in ranges.iter() {
-        let task_id = task_id.to_owned();
-        let total_hours = time_ranges::working_houres_from_ranges(
-            task_ranges,
-            Some(since),
-            Some(till),
-            &calendar,
-        );
-        let month_hours = time_ranges::month_hours(task_ranges, Some(since), Some(till), &calendar);
-        let months_vec: Vec<u32> = month_hours
-            .iter()
-            .map(|(k, _)| *k)
-            .map(|m| m.number_from_month())
-            .collect();
-        let month_range = (
-            *months_vec.iter().min().unwrap(),
-            *months_vec.iter().max().unwrap(),
-        );
+    let format = if args.csv {
+        ReportFormat::Csv
+    } else {
+        args.format
+    };
+    let options = report::ReportOptions {
+        since: args.since.start_datetime(),
+        till: args.till.end_datetime(),
+        hours: hours_kind(args.hours),
+        notes: args.notes,
+        budget: args.budget,
+        absences: args.absences,
+        wp_history: args.wp_history,
+        totals: !args.no_totals,
+        rounding: rounding(db, &args)?,
+    };
 
-        let month_range = (
-            chrono::Month::from_u32(month_range.0).unwrap(),
-            chrono::Month::from_u32(month_range.1).unwrap(),
-        );
-        reports.push(TaskReport {
-            task_id: task_id,
-            total_hours: total_hours,
-            month_range: month_range,
-            month_hours: month_hours,
-        });
+    if let Some(name) = args.template {
+        return report_template(db, &options, &name);
+    }
+
+    if let Some(path) = args.xlsx {
+        let report = report::build_workbook_report(db, &options)?;
+        let mut file = std::fs::File::create(&path)?;
+        render::XlsxRenderer.render(&report, &mut file)?;
+        println!("Report written to {}.", path.display());
+        return Ok(0);
+    }
+
+    let report = report::build_report(db, &options)?;
+
+    let renderer: Box<dyn render::Renderer> = match format {
+        ReportFormat::Table => Box::new(render::TableRenderer),
+        ReportFormat::Csv => Box::new(render::CsvRenderer),
+        ReportFormat::Json => Box::new(render::JsonRenderer),
+        ReportFormat::Markdown => Box::new(render::MarkdownRenderer),
+        ReportFormat::Html => Box::new(render::HtmlRenderer),
+    };
+    renderer.render(&report, &mut std::io::stdout())?;
+
+    Ok(0)
+}
+
+fn report_template(db: &Database, options: &report::ReportOptions, name: &str) -> CmdResult {
+    let source = match template::builtin_template(name) {
+        Some(source) => source.to_owned(),
+        None => match std::fs::read_to_string(name) {
+            Ok(source) => source,
+            Err(e) => {
+                println!("*** Can't read template {}: {} ***", name, e);
+                return Ok(1);
+            }
+        },
+    };
+    let data = template::template_data(db, options)?;
+    match template::render_template(name, &source, &data) {
+        Ok(text) => {
+            print!("{}", text);
+            Ok(0)
+        }
+        Err(error::Error::Template(e)) => {
+            let mut message = e.to_string();
+            let mut source = std::error::Error::source(&e);
+            while let Some(cause) = source {
+                message.push_str(&format!(": {}", cause));
+                source = cause.source();
+            }
+            println!("*** {} ***", message);
+            Ok(1)
+        }
+        Err(e) => Err(e),
     }
+}
 
-    reports.sort_by(|a, b| a.task_id.cmp(&b.task_id));
+fn balance(db: &Database, args: BalanceArgs) -> CmdResult {
+    use prettytable::{format::FormatBuilder, row, Table};
 
-    use prettytable::{cell, format::FormatBuilder, Cell, Row, Table};
+    let by = match args.by {
+        BalancePeriod::Day => balance::BalancePeriod::Day,
+        BalancePeriod::Week => balance::BalancePeriod::Week,
+        BalancePeriod::Month => balance::BalancePeriod::Month,
+    };
+    let balance = match balance::flex_time_balance(
+        db,
+        args.since.map(|d| d.0.naive_utc()),
+        args.till.map(|d| d.end_datetime()),
+        by,
+    )? {
+        None => {
+            println!("*** No tracked time yet ***");
+            return Ok(1);
+        }
+        Some(balance) => balance,
+    };
 
     let format = FormatBuilder::new()
         .column_separator(' ')
         .borders(' ')
         .padding(0, 0)
         .build();
-
-    let month = time_ranges::month_range(since, till);
-
-    let none = "None".to_owned();
     let mut table = Table::new();
     table.set_format(format);
-    let mut header: Vec<String> = vec![
-        "Title",
-        "URL",
-        "Total hours",
-        "Month range",
-        "Workpackage",
-        "Objective",
-    ]
-    .iter()
-    .map(|s| s.to_string())
-    .collect();
-    header.extend(month.iter().map(|m| m.name()[..3].to_string()));
-    table.add_row(Row::new(
-        header.into_iter().map(|r| Cell::new(&r)).collect(),
-    ));
-    for tr in reports.iter() {
-        let task = db.get_task(&tr.task_id)?;
-        let task = match task {
-            Some(task) => task,
-            None => database::Task {
-                task_id: tr.task_id.clone(),
-                url: None,
-                title: None,
-                workpackage: None,
-                objective: None,
-            },
-        };
-        let mut row = Vec::new();
-        row.push(cell!(format!(
-            "[{}]{}",
-            task.task_id,
-            task.title.unwrap_or_else(|| none.clone())
-        )));
-        row.push(cell!(task.url.unwrap_or_else(|| none.clone())));
-        row.push(cell!(format!("{:.2}", tr.total_hours)));
-        let month_range = if tr.month_range.0 == tr.month_range.1 {
-            tr.month_range.0.name()[..3].to_string()
-        } else {
-            format!(
-                "{}-{}",
-                tr.month_range.0.name()[..3].to_string(),
-                tr.month_range.1.name()[..3].to_string()
-            )
-        };
-        row.push(cell!(month_range));
-        row.push(cell!(task.workpackage.unwrap_or_else(|| none.clone())));
-        row.push(cell!(task.objective.unwrap_or_else(|| none.clone())));
-        for m in month.iter() {
-            if tr.month_hours.contains_key(m) {
-                row.push(cell!(format!("{:.2}", tr.month_hours[m])));
-            } else {
-                row.push(cell!(""));
-            }
-        }
-        table.add_row(Row::new(row));
+    table.add_row(row!["Period", "Tracked", "Expected", "Overtime", "Balance"]);
+    for period in balance.periods.iter() {
+        table.add_row(row![
+            period.name,
+            format!("{:.2}", time_ranges::hours(period.tracked)),
+            format!("{:.2}", time_ranges::hours(period.expected)),
+            format!("{:+.2}", time_ranges::hours(period.overtime())),
+            format!("{:+.2}", time_ranges::hours(period.balance))
+        ]);
     }
-    if args.csv {
-        table
-            .to_csv(std::io::stdout())
-            .expect("Can't serialize to csv");
-    } else {
-        table.printstd();
-    }
-
+    table.printstd();
+    println!(
+        "Flex-time balance since {}: {:+.2} hours",
+        balance.start.format("%d.%m.%Y"),
+        time_ranges::hours(balance.balance)
+    );
     Ok(0)
 }
 
-fn activate_task(mut db: Database, args: ActivateArgs) -> CmdResult {
-    if !db.is_task_exist(&args.task_id)? {
-        println!("*** Task with id {} does not exist. ***", args.task_id);
+fn activate_task(db: &mut Database, args: ActivateArgs) -> CmdResult {
+    let task = match db.get_task(&args.task_id)? {
+        None => {
+            println!("*** Task with id {} does not exist. ***", args.task_id);
+            return Ok(1);
+        }
+        Some(task) => task,
+    };
+    if task.status != TaskStatus::Open {
+        println!(
+            "*** Task with id {} is {}. Reopen it first. ***",
+            args.task_id, task.status
+        );
         return Ok(1);
     }
     let r = db.activate_task(&args.task_id)?;
@@ -251,40 +511,250 @@ fn activate_task(mut db: Database, args: ActivateArgs) -> CmdResult {
             old_task_id, args.task_id
         ),
     }
+    warn_about_budget(db, &task)?;
     Ok(0)
 }
 
-fn is_primary_key_error(error: &rusqlite::Error) -> bool {
-    use rusqlite::ffi::{Error, ErrorCode};
-    const PRIMARY_KEY_ERROR: Error = Error {
-        code: ErrorCode::ConstraintViolation,
-        extended_code: 1555,
+fn warn_about_budget(db: &Database, task: &database::Task) -> error::Result<()> {
+    let threshold: f64 = settings::get_parsed(db, settings::BUDGET_WARNING, |v| v.parse().ok())?;
+    let calendar = db.work_calendar()?;
+    if let Some(budget) = task.budget {
+        let consumed = report::consumed_time(db, std::slice::from_ref(&task.task_id), &calendar)?;
+        if consumed >= time_ranges::from_hours(budget * threshold / 100.0) {
+            println!(
+                "Warning: task with id {} consumed {}",
+                task.task_id,
+                budget_summary(budget, consumed)
+            );
+        }
+    }
+    if let Some(wp) = task.workpackage.as_ref() {
+        if let Some(budget) = db.get_workpackage_budget(wp)? {
+            let consumed = report::consumed_time(db, &db.get_workpackage_tasks(wp)?, &calendar)?;
+            if consumed >= time_ranges::from_hours(budget * threshold / 100.0) {
+                println!(
+                    "Warning: workpackage {} consumed {}",
+                    wp,
+                    budget_summary(budget, consumed)
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn budget_summary(budget: f64, consumed: chrono::Duration) -> String {
+    let consumed = time_ranges::hours(consumed);
+    let share = if budget.is_finite() && budget > 0.0 {
+        format!(" ({:.0}%)", consumed / budget * 100.0)
+    } else {
+        String::new()
+    };
+    format!(
+        "{:.2} of {:.2} hours{}, remaining {:.2}",
+        consumed,
+        budget,
+        share,
+        budget - consumed
+    )
+}
+
+fn workpackage_budget(db: &Database, args: WorkpackageBudgetArgs) -> CmdResult {
+    if args.drop {
+        db.set_workpackage_budget(&args.workpackage, None)?;
+        println!("Budget of workpackage {} has dropped", args.workpackage);
+        return Ok(0);
+    }
+    if let Some(budget) = args.budget {
+        db.set_workpackage_budget(&args.workpackage, Some(budget))?;
+    }
+    match db.get_workpackage_budget(&args.workpackage)? {
+        None => {
+            println!("*** Workpackage {} has no budget ***", args.workpackage);
+            Ok(1)
+        }
+        Some(budget) => {
+            let tasks = db.get_workpackage_tasks(&args.workpackage)?;
+            let consumed = report::consumed_time(db, &tasks, &db.work_calendar()?)?;
+            println!(
+                "Workpackage {}: {}",
+                args.workpackage,
+                budget_summary(budget, consumed)
+            );
+            Ok(0)
+        }
+    }
+}
+
+fn config(db: &Database, args: ConfigArgs) -> CmdResult {
+    let key = match args.key {
+        None => {
+            for setting in settings::SETTINGS {
+                println!(
+                    "{} = {}\n\t{} (default {})",
+                    setting.key,
+                    settings::get(db, setting.key)?,
+                    setting.description,
+                    setting.default
+                );
+            }
+            return Ok(0);
+        }
+        Some(key) => key,
     };
-    match error {
-        rusqlite::Error::SqliteFailure(PRIMARY_KEY_ERROR, _) => true,
-        _ => false,
+    let setting = match settings::find(&key) {
+        None => {
+            println!("*** Unknown setting {} ***", key);
+            return Ok(1);
+        }
+        Some(setting) => setting,
+    };
+    if args.unset {
+        db.set_setting(setting.key, None)?;
+    } else if let Some(value) = args.value {
+        if !(setting.validate)(&value) {
+            println!("*** Invalid value {} for setting {} ***", value, key);
+            return Ok(1);
+        }
+        db.set_setting(setting.key, Some(&value))?;
+    }
+    println!("{} = {}", setting.key, settings::get(db, setting.key)?);
+    Ok(0)
+}
+
+fn undo(db: &mut Database) -> CmdResult {
+    match db.undo()? {
+        None => {
+            println!("*** Nothing to undo ***");
+            Ok(1)
+        }
+        Some(operation) => {
+            println!(
+                "Undone: {} ({})",
+                operation.description,
+                operation.timestamp.format("%d.%m.%Y %H:%M:%S")
+            );
+            Ok(0)
+        }
+    }
+}
+
+fn redo(db: &mut Database) -> CmdResult {
+    match db.redo()? {
+        None => {
+            println!("*** Nothing to redo ***");
+            Ok(1)
+        }
+        Some(operation) => {
+            println!(
+                "Redone: {} ({})",
+                operation.description,
+                operation.timestamp.format("%d.%m.%Y %H:%M:%S")
+            );
+            Ok(0)
+        }
+    }
+}
+
+fn diagnose(db: &Database) -> error::Result<Vec<doctor::Issue>> {
+    let tasks = db
+        .list_tasks(None, true)?
+        .into_iter()
+        .map(|t| t.task_id)
+        .collect();
+    Ok(doctor::diagnose(
+        &db.get_range_events()?,
+        &tasks,
+        db.clock().now(),
+    ))
+}
+
+fn confirm(question: &str) -> error::Result<bool> {
+    use std::io::Write;
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn doctor(db: &mut Database, args: DoctorArgs) -> CmdResult {
+    if !args.fix && !args.interactive {
+        let issues = diagnose(db)?;
+        for issue in issues.iter() {
+            println!("{}", issue.description);
+        }
+        if issues.is_empty() {
+            println!("No issues found");
+            return Ok(0);
+        }
+        println!(
+            "*** {} issue(s) found, run with --fix to repair ***",
+            issues.len()
+        );
+        return Ok(1);
+    }
+
+    // Fixing one issue may resolve or change others, so diagnose again after each fix.
+    let mut skipped = std::collections::HashSet::new();
+    let mut fixed = 0;
+    loop {
+        let issue = match diagnose(db)?
+            .into_iter()
+            .find(|i| !skipped.contains(&i.description))
+        {
+            None => break,
+            Some(issue) => issue,
+        };
+        println!("{}", issue.description);
+        if args.interactive && !confirm("Fix?")? {
+            skipped.insert(issue.description);
+            continue;
+        }
+        db.apply_range_fixes(&issue.fixes)?;
+        skipped.insert(issue.description);
+        fixed += 1;
+    }
+    println!("{} issue(s) fixed", fixed);
+    Ok(0)
+}
+
+fn set_task_status(db: &mut Database, args: StatusArgs, status: TaskStatus) -> CmdResult {
+    match db.set_task_status(&args.task_id, status)? {
+        None => {
+            println!("*** Task with id {} does not exist. ***", args.task_id);
+            Ok(1)
+        }
+        Some(old_status) if old_status == status => {
+            println!("Task with id {} is already {}", args.task_id, status);
+            Ok(0)
+        }
+        Some(old_status) => {
+            println!(
+                "Task with id {} has changed status from {} to {}",
+                args.task_id, old_status, status
+            );
+            Ok(0)
+        }
     }
 }
 
 fn new_task(db: &Database, args: NewArgs) -> CmdResult {
     match db.new_task(
         &args.task_id,
-        args.url.as_ref().map(|s| s.as_str()),
-        args.title.as_ref().map(|s| s.as_str()),
-        args.workpackage.as_ref().map(|s| s.as_str()),
-        args.objective.as_ref().map(|s| s.as_str()),
+        args.url.as_deref(),
+        args.title.as_deref(),
+        args.workpackage.as_deref(),
+        args.objective.as_deref(),
+        args.budget,
     ) {
         Ok(()) => {
             println!("New task with id {} has created.", args.task_id);
             Ok(0)
         }
-        Err(error::Error::SQL(err)) => {
-            if is_primary_key_error(&err) {
-                println!("*** Task with id {} already exists. ***", args.task_id);
-                Ok(1)
-            } else {
-                Err(error::Error::SQL(err))
-            }
+        Err(error::Error::TaskExists) => {
+            println!("*** Task with id {} already exists. ***", args.task_id);
+            Ok(1)
         }
         Err(err) => Err(err),
     }
@@ -293,33 +763,116 @@ fn new_task(db: &Database, args: NewArgs) -> CmdResult {
 fn edit_task(db: &Database, args: EditArgs) -> CmdResult {
     let (found, was_fields) = db.update_task(
         &args.task_id,
-        args.url.as_ref().map(|s| s.as_str()),
-        args.title.as_ref().map(|s| s.as_str()),
-        args.workpackage.as_ref().map(|s| s.as_str()),
-        args.objective.as_ref().map(|s| s.as_str()),
+        args.url.as_deref(),
+        args.title.as_deref(),
+        args.workpackage.as_deref(),
+        args.objective.as_deref(),
+        args.budget,
         args.drop_url,
         args.drop_title,
         args.drop_workpackage,
         args.drop_objective,
+        args.drop_budget,
     )?;
 
     if !was_fields {
         println!("*** Not values for update ***");
-        return Ok(2);
+        Ok(2)
     } else if !found {
         println!(
             "*** Task with id {} has not updated. Probably it does not exist. ***",
             args.task_id
         );
-        return Ok(1);
+        Ok(1)
     } else {
         println!("Task with id {} has updated", args.task_id);
-        return show_task(
+        show_task(
             db,
             ShowArgs {
                 task_id: args.task_id,
+                history: false,
             },
-        );
+        )
+    }
+}
+
+fn delete_task(db: &mut Database, args: DeleteArgs) -> CmdResult {
+    match db.delete_task(&args.task_id, args.cascade)? {
+        DeleteStatus::Deleted => {
+            println!("Task with id {} has deleted", args.task_id);
+            Ok(0)
+        }
+        DeleteStatus::NotFound => {
+            println!("*** Task with id {} does not exist. ***", args.task_id);
+            Ok(1)
+        }
+        DeleteStatus::HasRanges(n) => {
+            println!(
+                "*** Task with id {} has {} time range records. Use --cascade to delete them too. ***",
+                args.task_id, n
+            );
+            Ok(1)
+        }
+    }
+}
+
+fn rename_task(db: &mut Database, args: RenameArgs) -> CmdResult {
+    match db.rename_task(&args.task_id, &args.new_task_id)? {
+        RenameStatus::Renamed => {
+            println!(
+                "Task with id {} has renamed to {}",
+                args.task_id, args.new_task_id
+            );
+            Ok(0)
+        }
+        RenameStatus::NotFound => {
+            println!("*** Task with id {} does not exist. ***", args.task_id);
+            Ok(1)
+        }
+        RenameStatus::AlreadyExists => {
+            println!("*** Task with id {} already exists. ***", args.new_task_id);
+            Ok(1)
+        }
+    }
+}
+
+fn merge_tasks(db: &mut Database, args: MergeArgs) -> CmdResult {
+    match db.merge_tasks(&args.source, &args.target)? {
+        MergeStatus::Merged => {
+            println!(
+                "Task with id {} has merged into {}",
+                args.source, args.target
+            );
+            show_task(
+                db,
+                ShowArgs {
+                    task_id: args.target,
+                    history: false,
+                },
+            )
+        }
+        MergeStatus::SourceNotFound => {
+            println!("*** Task with id {} does not exist. ***", args.source);
+            Ok(1)
+        }
+        MergeStatus::TargetNotFound => {
+            println!("*** Task with id {} does not exist. ***", args.target);
+            Ok(1)
+        }
+        MergeStatus::SameTask => {
+            println!(
+                "*** Can't merge task with id {} into itself. ***",
+                args.source
+            );
+            Ok(1)
+        }
+        MergeStatus::OpenRange(task_id) => {
+            println!(
+                "*** Task with id {} has a time range which is never stopped. Fix it with doctor first. ***",
+                task_id
+            );
+            Ok(1)
+        }
     }
 }
 
@@ -328,7 +881,7 @@ fn show_task(db: &Database, args: ShowArgs) -> CmdResult {
     match task {
         None => {
             println!("*** No task found with id {}. ***", args.task_id);
-            return Ok(1);
+            Ok(1)
         }
         Some(task) => {
             let none = "None".to_owned();
@@ -340,18 +893,93 @@ fn show_task(db: &Database, args: ShowArgs) -> CmdResult {
                 task.workpackage.as_ref().unwrap_or(&none)
             );
             println!("\tObjective: {}", task.objective.as_ref().unwrap_or(&none));
+            println!("\tStatus: {}", task.status);
+            if task.budget.is_some() || task.workpackage.is_some() {
+                let calendar = db.work_calendar()?;
+                if let Some(budget) = task.budget {
+                    let consumed =
+                        report::consumed_time(db, std::slice::from_ref(&task.task_id), &calendar)?;
+                    println!("\tBudget: {}", budget_summary(budget, consumed));
+                }
+                if let Some(wp) = task.workpackage.as_ref() {
+                    if let Some(budget) = db.get_workpackage_budget(wp)? {
+                        let consumed =
+                            report::consumed_time(db, &db.get_workpackage_tasks(wp)?, &calendar)?;
+                        println!("\tWorkpackage budget: {}", budget_summary(budget, consumed));
+                    }
+                }
+            }
+            if args.history {
+                println!("\tHistory:");
+                for change in db.get_task_history(&task.task_id)? {
+                    println!(
+                        "\t\t{} {}: {} -> {}",
+                        change.timestamp.format("%d.%m.%Y %H:%M"),
+                        change.field,
+                        change.old_value.as_ref().unwrap_or(&none),
+                        change.new_value.as_ref().unwrap_or(&none)
+                    );
+                }
+            }
+            let notes = db.get_notes(Some(&task.task_id), None, None)?;
+            if !notes.is_empty() {
+                println!("\tNotes:");
+                for note in notes {
+                    let range = match note.range_start {
+                        Some(start) => format!(" [range since {}]", start.format("%d.%m.%Y %H:%M")),
+                        None => "".to_owned(),
+                    };
+                    println!(
+                        "\t\t{} {}{} {}",
+                        note.note_id,
+                        note.timestamp.format("%d.%m.%Y %H:%M"),
+                        range,
+                        note.text
+                    );
+                }
+            }
             Ok(0)
         }
     }
 }
 
+fn add_note(db: &Database, args: NoteArgs) -> CmdResult {
+    let (task_id, text) = match args.text {
+        Some(text) => (args.task_or_text, text),
+        None => match db.get_current_task_id()? {
+            Some(task_id) => (task_id, args.task_or_text),
+            None => {
+                println!("*** No current task ***");
+                return Ok(1);
+            }
+        },
+    };
+    if db.get_task(&task_id)?.is_none() {
+        println!("*** Task with id {} does not exist. ***", task_id);
+        return Ok(1);
+    }
+    let range_start = if args.attach {
+        let range_start = db.get_active_range_start(&task_id)?;
+        if range_start.is_none() {
+            println!("*** Task with id {} is not active. ***", task_id);
+            return Ok(1);
+        }
+        range_start
+    } else {
+        None
+    };
+    db.add_note(&task_id, &text, range_start)?;
+    println!("Note added to task with id {}", task_id);
+    Ok(0)
+}
+
 fn list_tasks(db: &Database, args: ListArgs) -> CmdResult {
     use prettytable::{format::FormatBuilder, Cell, Row, Table};
     let current_task = db.get_current_task_id()?;
-    let tasks = db.list_tasks(args.num_tasks)?;
+    let tasks = db.list_tasks(args.num_tasks, args.all)?;
     if tasks.is_empty() {
         println!("*** No task created yet ***");
-        return Ok(1);
+        Ok(1)
     } else {
         let format = FormatBuilder::new()
             .column_separator(' ')
@@ -374,40 +1002,40 @@ fn list_tasks(db: &Database, args: ListArgs) -> CmdResult {
             } else {
                 row.push(Cell::new(""));
             }
+            if task.status != TaskStatus::Open {
+                row.push(Cell::new(&format!("({})", task.status)));
+            }
             table.add_row(Row::new(row));
         }
 
         table.printstd();
-        return Ok(0);
+        Ok(0)
     }
 }
 
-fn get_calendar(
-    db: &Database,
-) -> error::Result<impl bdays::HolidayCalendar<time_ranges::DateTime>> {
-    let vacations = db.get_vacations()?;
-    Ok(time_ranges::CalendarCombination::holydays_and_vacations(
-        vacations,
-    ))
-}
-
 fn current_task(db: &Database) -> CmdResult {
     if let Some(task_id) = db.get_current_task_id()? {
         let time_ranges_map = db.select_time_ranges(Some(&task_id), None, None)?;
         let time_ranges = time_ranges_map.get(&task_id);
         let working_houers = match time_ranges {
-            None => 0.0,
-            Some(ranges) => {
-                time_ranges::working_houres_from_ranges(ranges, None, None, &get_calendar(db)?)
-            }
+            None => time_ranges::WorkingHours::default(),
+            Some(ranges) => time_ranges::working_houres_from_ranges(
+                ranges,
+                None,
+                None,
+                &db.work_calendar()?,
+                db.clock(),
+            ),
         };
         println!(
-            "Current task: {}. You are working on it for {:.4} hours",
-            task_id, working_houers
+            "Current task: {}. You are working on it for {:.4} hours ({:.4} tracked)",
+            task_id,
+            time_ranges::hours(working_houers.normalized),
+            time_ranges::hours(working_houers.raw)
         );
-        return Ok(0);
+        Ok(0)
     } else {
         println!("*** No current task ***");
-        return Ok(1);
+        Ok(1)
     }
 }
@@ -0,0 +1,567 @@
+//! Renderers turning [`Report`] into text formats.
+
+use std::io::Write;
+
+use rust_xlsxwriter::{
+    utility::cell_range, ColNum, Format, Formula, Workbook, Worksheet, XlsxError,
+};
+use serde_json::{json, Map, Value};
+
+use crate::error::Result;
+use crate::report::{Cell, Report, ReportTable};
+use crate::time_ranges;
+
+/// Writes report in some format.
+pub trait Renderer {
+    /// Writes the report into `out`.
+    fn render(&self, report: &Report, out: &mut dyn Write) -> Result<()>;
+}
+
+fn prettytable(table: &ReportTable) -> prettytable::Table {
+    use prettytable::format::FormatBuilder;
+
+    let format = FormatBuilder::new()
+        .column_separator(' ')
+        .borders(' ')
+        .padding(0, 0)
+        .build();
+
+    let row = |cells: Vec<String>| {
+        prettytable::Row::new(cells.iter().map(|c| prettytable::Cell::new(c)).collect())
+    };
+    let mut result = prettytable::Table::new();
+    result.set_format(format);
+    result.add_row(row(table.columns.clone()));
+    for cells in table.rows.iter().chain(table.totals.iter()) {
+        result.add_row(row(cells.iter().map(Cell::display).collect()));
+    }
+    result
+}
+
+/// Plain text table aligned for terminal.
+pub struct TableRenderer;
+
+impl Renderer for TableRenderer {
+    fn render(&self, report: &Report, out: &mut dyn Write) -> Result<()> {
+        for (i, table) in report.tables.iter().enumerate() {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            prettytable(table).print(out)?;
+        }
+        Ok(())
+    }
+}
+
+/// Comma separated values, tables are separated by empty line.
+pub struct CsvRenderer;
+
+impl Renderer for CsvRenderer {
+    fn render(&self, report: &Report, out: &mut dyn Write) -> Result<()> {
+        for (i, table) in report.tables.iter().enumerate() {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            prettytable(table)
+                .to_csv(&mut *out)
+                .map_err(std::io::Error::other)?;
+        }
+        Ok(())
+    }
+}
+
+/// JSON document with rows as objects keyed by column names.
+pub struct JsonRenderer;
+
+fn json_cell(cell: &Cell) -> Value {
+    match cell {
+        Cell::Empty | Cell::Missing => Value::Null,
+        Cell::Text(text) => json!(text),
+        Cell::Hours(hours) => json!(time_ranges::hours(*hours)),
+        Cell::HoursPair(normalized, raw) => json!({
+            "normalized": time_ranges::hours(*normalized),
+            "raw": time_ranges::hours(*raw),
+        }),
+        Cell::Days(days) => json!(days),
+        Cell::Percent(percent) => json!(percent),
+    }
+}
+
+fn json_row(columns: &[String], cells: &[Cell]) -> Value {
+    let row: Map<String, Value> = columns
+        .iter()
+        .zip(cells.iter())
+        .map(|(column, cell)| (column.clone(), json_cell(cell)))
+        .collect();
+    Value::Object(row)
+}
+
+impl Renderer for JsonRenderer {
+    fn render(&self, report: &Report, out: &mut dyn Write) -> Result<()> {
+        let tables: Vec<Value> = report
+            .tables
+            .iter()
+            .map(|table| {
+                json!({
+                    "title": table.title,
+                    "columns": table.columns,
+                    "rows": table
+                        .rows
+                        .iter()
+                        .map(|row| json_row(&table.columns, row))
+                        .collect::<Vec<_>>(),
+                    "totals": table.totals.as_ref().map(|row| json_row(&table.columns, row)),
+                })
+            })
+            .collect();
+        let document = json!({
+            "since": report.since.to_rfc3339(),
+            "till": report.till.to_rfc3339(),
+            "tables": tables,
+        });
+        serde_json::to_writer_pretty(&mut *out, &document).map_err(std::io::Error::from)?;
+        writeln!(out)?;
+        Ok(())
+    }
+}
+
+fn report_title(report: &Report) -> String {
+    format!(
+        "Report {} - {}",
+        report.since.format("%d.%m.%Y"),
+        report.till.format("%d.%m.%Y")
+    )
+}
+
+/// Column is right aligned when it holds numbers.
+fn numeric_columns(table: &ReportTable) -> Vec<bool> {
+    (0..table.columns.len())
+        .map(|i| {
+            table
+                .rows
+                .iter()
+                .chain(table.totals.iter())
+                .any(|row| row.get(i).is_some_and(Cell::is_numeric))
+        })
+        .collect()
+}
+
+/// GitHub flavored Markdown with one pipe table per report table.
+pub struct MarkdownRenderer;
+
+fn markdown_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace('\n', " ")
+}
+
+fn markdown_row(out: &mut dyn Write, cells: &[String]) -> Result<()> {
+    writeln!(out, "| {} |", cells.join(" | "))?;
+    Ok(())
+}
+
+impl Renderer for MarkdownRenderer {
+    fn render(&self, report: &Report, out: &mut dyn Write) -> Result<()> {
+        writeln!(out, "# {}", report_title(report))?;
+        for table in report.tables.iter() {
+            writeln!(out)?;
+            writeln!(out, "## {}", markdown_escape(&table.title))?;
+            writeln!(out)?;
+            let columns: Vec<String> = table.columns.iter().map(|c| markdown_escape(c)).collect();
+            markdown_row(out, &columns)?;
+            let alignment: Vec<String> = numeric_columns(table)
+                .into_iter()
+                .map(|numeric| if numeric { "---:" } else { "---" }.to_owned())
+                .collect();
+            markdown_row(out, &alignment)?;
+            for row in table.rows.iter() {
+                let cells: Vec<String> =
+                    row.iter().map(|c| markdown_escape(&c.display())).collect();
+                markdown_row(out, &cells)?;
+            }
+            if let Some(totals) = &table.totals {
+                let cells: Vec<String> = totals
+                    .iter()
+                    .map(|c| match c {
+                        Cell::Empty => String::new(),
+                        c => format!("**{}**", markdown_escape(&c.display())),
+                    })
+                    .collect();
+                markdown_row(out, &cells)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Self-contained HTML page with inline styles, suitable for mail.
+pub struct HtmlRenderer;
+
+const TABLE_STYLE: &str = "border-collapse: collapse; margin-bottom: 1.5em;";
+const CELL_STYLE: &str = "border: 1px solid #ccc; padding: 2px 6px;";
+const HEADER_STYLE: &str = " background: #eee;";
+const TOTALS_STYLE: &str = " font-weight: bold; border-top: 2px solid #666;";
+
+fn html_escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+fn html_row(
+    out: &mut dyn Write,
+    tag: &str,
+    style: &str,
+    cells: &[String],
+    numeric: &[bool],
+) -> Result<()> {
+    write!(out, "<tr>")?;
+    for (cell, numeric) in cells.iter().zip(numeric.iter()) {
+        let align = if *numeric { " text-align: right;" } else { "" };
+        write!(
+            out,
+            "<{tag} style=\"{CELL_STYLE}{style}{align}\">{}</{tag}>",
+            html_escape(cell)
+        )?;
+    }
+    writeln!(out, "</tr>")?;
+    Ok(())
+}
+
+impl Renderer for HtmlRenderer {
+    fn render(&self, report: &Report, out: &mut dyn Write) -> Result<()> {
+        let title = html_escape(&report_title(report));
+        writeln!(out, "<!DOCTYPE html>")?;
+        writeln!(out, "<html>")?;
+        writeln!(out, "<head>")?;
+        writeln!(out, "<meta charset=\"utf-8\">")?;
+        writeln!(out, "<title>{}</title>", title)?;
+        writeln!(out, "</head>")?;
+        writeln!(
+            out,
+            "<body style=\"font-family: sans-serif; font-size: 14px;\">"
+        )?;
+        writeln!(out, "<h1>{}</h1>", title)?;
+        for table in report.tables.iter() {
+            let numeric = numeric_columns(table);
+            writeln!(out, "<h2>{}</h2>", html_escape(&table.title))?;
+            writeln!(out, "<table style=\"{TABLE_STYLE}\">")?;
+            writeln!(out, "<thead>")?;
+            let left = vec![false; table.columns.len()];
+            html_row(out, "th", HEADER_STYLE, &table.columns, &left)?;
+            writeln!(out, "</thead>")?;
+            writeln!(out, "<tbody>")?;
+            for row in table.rows.iter() {
+                let cells: Vec<String> = row.iter().map(Cell::display).collect();
+                html_row(out, "td", "", &cells, &numeric)?;
+            }
+            writeln!(out, "</tbody>")?;
+            if let Some(totals) = &table.totals {
+                writeln!(out, "<tfoot>")?;
+                let cells: Vec<String> = totals.iter().map(Cell::display).collect();
+                html_row(out, "td", TOTALS_STYLE, &cells, &numeric)?;
+                writeln!(out, "</tfoot>")?;
+            }
+            writeln!(out, "</table>")?;
+        }
+        writeln!(out, "</body>")?;
+        writeln!(out, "</html>")?;
+        Ok(())
+    }
+}
+
+/// Excel workbook with worksheet per table, numbers are typed cells and totals are formulas.
+/// There is no OpenDocument counterpart, `spreadsheet-ods` needs newer chrono than the crate uses.
+pub struct XlsxRenderer;
+
+/// Numbers of the cell, pair of hours takes two spreadsheet columns.
+fn xlsx_numbers(cell: &Cell) -> Vec<f64> {
+    match cell {
+        Cell::Hours(hours) => vec![time_ranges::hours(*hours)],
+        Cell::HoursPair(normalized, raw) => {
+            vec![time_ranges::hours(*normalized), time_ranges::hours(*raw)]
+        }
+        Cell::Days(value) | Cell::Percent(value) => vec![*value],
+        Cell::Empty | Cell::Missing | Cell::Text(_) => Vec::new(),
+    }
+}
+
+/// First spreadsheet column of every table column and whether it holds pairs of hours.
+fn xlsx_layout(table: &ReportTable) -> Vec<(ColNum, bool)> {
+    let mut col = 0;
+    (0..table.columns.len())
+        .map(|i| {
+            let pair = table
+                .rows
+                .iter()
+                .chain(table.totals.iter())
+                .any(|row| matches!(row.get(i), Some(Cell::HoursPair(..))));
+            let first = col;
+            col += if pair { 2 } else { 1 };
+            (first, pair)
+        })
+        .collect()
+}
+
+fn xlsx_table(sheet: &mut Worksheet, table: &ReportTable) -> std::result::Result<(), XlsxError> {
+    let header = Format::new().set_bold();
+    let number = |cell: &Cell| match cell {
+        Cell::Days(_) => Format::new().set_num_format("0.0"),
+        Cell::Percent(_) => Format::new().set_num_format("0.0\"%\""),
+        _ => Format::new().set_num_format("0.00"),
+    };
+
+    let layout = xlsx_layout(table);
+
+    sheet.set_name(&table.title)?;
+    for (column, (col, pair)) in table.columns.iter().zip(layout.iter().copied()) {
+        if pair {
+            sheet.write_string_with_format(0, col, format!("{} normalized", column), &header)?;
+            sheet.write_string_with_format(0, col + 1, format!("{} tracked", column), &header)?;
+        } else {
+            sheet.write_string_with_format(0, col, column, &header)?;
+        }
+    }
+
+    let last_row = table.rows.len() as u32;
+    for (row_num, row) in (1..).zip(table.rows.iter()) {
+        for (cell, (col, _)) in row.iter().zip(layout.iter().copied()) {
+            match cell {
+                Cell::Empty => {}
+                Cell::Missing | Cell::Text(_) => {
+                    sheet.write_string(row_num, col, cell.display())?;
+                }
+                _ => {
+                    for (offset, value) in (0..).zip(xlsx_numbers(cell)) {
+                        sheet.write_number_with_format(
+                            row_num,
+                            col + offset,
+                            value,
+                            &number(cell),
+                        )?;
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(totals) = &table.totals {
+        let row_num = last_row + 1;
+        for (cell, (col, _)) in totals.iter().zip(layout.iter().copied()) {
+            match cell {
+                Cell::Empty | Cell::Missing => {}
+                Cell::Text(text) => {
+                    sheet.write_string_with_format(row_num, col, text, &header)?;
+                }
+                _ => {
+                    for (offset, value) in (0..).zip(xlsx_numbers(cell)) {
+                        let range = cell_range(1, col + offset, last_row.max(1), col + offset);
+                        let formula =
+                            Formula::new(format!("=SUM({})", range)).set_result(value.to_string());
+                        sheet.write_formula_with_format(
+                            row_num,
+                            col + offset,
+                            formula,
+                            &number(cell).set_bold(),
+                        )?;
+                    }
+                }
+            }
+        }
+    }
+
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofit();
+    Ok(())
+}
+
+impl Renderer for XlsxRenderer {
+    fn render(&self, report: &Report, out: &mut dyn Write) -> Result<()> {
+        let mut workbook = Workbook::new();
+        for table in report.tables.iter() {
+            xlsx_table(workbook.add_worksheet(), table).map_err(std::io::Error::other)?;
+        }
+        let buffer = workbook.save_to_buffer().map_err(std::io::Error::other)?;
+        out.write_all(&buffer)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        xlsx_layout, CsvRenderer, HtmlRenderer, JsonRenderer, MarkdownRenderer, Renderer,
+        XlsxRenderer,
+    };
+    use crate::report::{Cell, Report, ReportTable};
+    use chrono::{Duration, TimeZone};
+
+    fn report(rows: Vec<Vec<Cell>>, totals: Option<Vec<Cell>>) -> Report {
+        let table = ReportTable {
+            title: "Tasks".to_owned(),
+            columns: [
+                "Title",
+                "URL",
+                "Total/tracked hours",
+                "Clipped hours",
+                "Mar",
+            ]
+            .iter()
+            .map(|c| c.to_string())
+            .collect(),
+            rows,
+            totals,
+        };
+        Report {
+            since: chrono::Utc.ymd(2023, 3, 1).and_hms(0, 0, 0),
+            till: chrono::Utc.ymd(2023, 3, 31).and_hms(23, 59, 59),
+            tables: vec![table],
+        }
+    }
+
+    fn task_row(title: &str) -> Vec<Cell> {
+        vec![
+            Cell::Text(title.to_owned()),
+            Cell::Missing,
+            Cell::HoursPair(Duration::hours(3), Duration::minutes(330)),
+            Cell::Hours(Duration::minutes(90)),
+            Cell::Empty,
+        ]
+    }
+
+    fn total_row() -> Vec<Cell> {
+        vec![
+            Cell::Text("Total".to_owned()),
+            Cell::Empty,
+            Cell::HoursPair(Duration::hours(3), Duration::minutes(330)),
+            Cell::Hours(Duration::minutes(90)),
+            Cell::Empty,
+        ]
+    }
+
+    fn render(renderer: &dyn Renderer, report: &Report) -> String {
+        let mut out = Vec::new();
+        renderer.render(report, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn csv_test() {
+        let report = report(vec![task_row("[A]Title, with comma")], None);
+        assert_eq!(
+            render(&CsvRenderer, &report),
+            "Title,URL,Total/tracked hours,Clipped hours,Mar\n\
+             \"[A]Title, with comma\",None,3.00/5.50,1.50,\n"
+        );
+    }
+
+    #[test]
+    fn json_test() {
+        let report = report(vec![task_row("[A]Title")], Some(total_row()));
+        let value: serde_json::Value =
+            serde_json::from_str(&render(&JsonRenderer, &report)).unwrap();
+        let table = &value["tables"][0];
+        assert_eq!(table["title"], "Tasks");
+        assert_eq!(table["columns"][2], "Total/tracked hours");
+        let row = &table["rows"][0];
+        assert_eq!(row["Title"], "[A]Title");
+        assert!(row["URL"].is_null());
+        assert_eq!(row["Total/tracked hours"]["normalized"], 3.0);
+        assert_eq!(row["Total/tracked hours"]["raw"], 5.5);
+        assert_eq!(row["Clipped hours"], 1.5);
+        assert!(row["Mar"].is_null());
+        assert_eq!(table["totals"]["Title"], "Total");
+        assert_eq!(value["since"], "2023-03-01T00:00:00+00:00");
+    }
+
+    #[test]
+    fn markdown_escape_test() {
+        let report = report(vec![task_row("[A]a|b\\c\nd")], None);
+        let markdown = render(&MarkdownRenderer, &report);
+        let lines: Vec<&str> = markdown.lines().collect();
+        assert_eq!(lines[0], "# Report 01.03.2023 - 31.03.2023");
+        assert_eq!(
+            lines[4],
+            "| Title | URL | Total/tracked hours | Clipped hours | Mar |"
+        );
+        assert_eq!(lines[5], "| --- | --- | ---: | ---: | --- |");
+        assert_eq!(lines[6], "| [A]a\\|b\\\\c d | None | 3.00/5.50 | 1.50 |  |");
+    }
+
+    #[test]
+    fn html_escape_test() {
+        let report = report(vec![task_row("<b>\"A\" & 'B'</b>")], None);
+        let html = render(&HtmlRenderer, &report);
+        assert!(html.contains(">&lt;b&gt;&quot;A&quot; &amp; &#39;B&#39;&lt;/b&gt;</td>"));
+        assert!(!html.contains("<b>"));
+    }
+
+    #[test]
+    fn markdown_totals_test() {
+        let mut report = report(vec![task_row("[A]Title")], Some(total_row()));
+        report.tables[0].title = "Tasks | 2023".to_owned();
+        let markdown = render(&MarkdownRenderer, &report);
+        let lines: Vec<&str> = markdown.lines().collect();
+        assert_eq!(lines[2], "## Tasks \\| 2023");
+        assert_eq!(
+            lines.last().copied(),
+            Some("| **Total** |  | **3.00/5.50** | **1.50** |  |")
+        );
+    }
+
+    #[test]
+    fn html_totals_test() {
+        let report = report(vec![task_row("[A]Title")], Some(total_row()));
+        let html = render(&HtmlRenderer, &report);
+        assert!(html.contains("<title>Report 01.03.2023 - 31.03.2023</title>"));
+        assert!(html.contains("<h2>Tasks</h2>"));
+        let footer = html.split("<tfoot>").nth(1).unwrap();
+        assert!(footer.starts_with(
+            "\n<tr><td style=\"border: 1px solid #ccc; padding: 2px 6px; font-weight: bold;"
+        ));
+        assert!(footer.contains("text-align: right;\">3.00/5.50</td>"));
+        assert_eq!(html.matches("<th ").count(), 5);
+        assert!(!html.contains(
+            "<th style=\"border: 1px solid #ccc; padding: 2px 6px; background: #eee; text-align"
+        ));
+    }
+
+    #[test]
+    fn xlsx_sheets_test() {
+        let mut report = report(vec![task_row("[A]Title")], Some(total_row()));
+        report.tables.push(ReportTable {
+            title: "Vacations".to_owned(),
+            columns: vec!["Id".to_owned()],
+            rows: vec![vec![Cell::Text("1".to_owned())]],
+            totals: None,
+        });
+        let mut out = Vec::new();
+        XlsxRenderer.render(&report, &mut out).unwrap();
+        // Names of zip entries are stored uncompressed.
+        let contains = |name: &str| out.windows(name.len()).any(|w| w == name.as_bytes());
+        assert!(out.starts_with(b"PK"));
+        assert!(contains("xl/worksheets/sheet1.xml"));
+        assert!(contains("xl/worksheets/sheet2.xml"));
+        assert!(!contains("xl/worksheets/sheet3.xml"));
+    }
+
+    #[test]
+    fn xlsx_layout_test() {
+        let mut row = task_row("[B]Title");
+        row[4] = Cell::HoursPair(Duration::hours(1), Duration::hours(2));
+        let report = report(vec![task_row("[A]Title"), row], None);
+        assert_eq!(
+            xlsx_layout(&report.tables[0]),
+            vec![(0, false), (1, false), (2, true), (4, false), (5, true)]
+        );
+    }
+}
@@ -0,0 +1,1048 @@
+//! Aggregation of tracked time for reports.
+//!
+//! [`build_report`] collects the data into a [`Report`], which is turned into text by
+//! a [`crate::render::Renderer`].
+
+use std::collections::HashMap;
+
+use chrono::{Datelike, NaiveDate};
+use num_traits::FromPrimitive;
+
+use crate::database::{Absence, AbsenceKind, Task};
+use crate::error::Result;
+use crate::storage::Storage;
+use crate::time_ranges::{self, Clock, DateTime, Rounding, TimeRange, WorkCalendar, WorkingHours};
+
+/// Hours shown in reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HoursKind {
+    /// Tracked wall-clock time.
+    Raw,
+    /// Hours inside working calendar.
+    Normalized,
+    /// Normalized and raw hours.
+    Both,
+}
+
+/// Value of a report cell.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    /// No value, e.g. month without tracked time.
+    Empty,
+    /// Value which is not set, shown as `None`.
+    Missing,
+    /// Text value.
+    Text(String),
+    /// Hours of one kind.
+    Hours(chrono::Duration),
+    /// Normalized and raw hours.
+    HoursPair(chrono::Duration, chrono::Duration),
+    /// Days of absence.
+    Days(f64),
+    /// Share in percent.
+    Percent(f64),
+}
+
+impl Cell {
+    fn add(&self, other: &Cell) -> Cell {
+        match (self, other) {
+            (Cell::Hours(a), Cell::Hours(b)) => Cell::Hours(*a + *b),
+            (Cell::HoursPair(a1, a2), Cell::HoursPair(b1, b2)) => {
+                Cell::HoursPair(*a1 + *b1, *a2 + *b2)
+            }
+            (Cell::Days(a), Cell::Days(b)) => Cell::Days(a + b),
+            (Cell::Percent(a), Cell::Percent(b)) => Cell::Percent(a + b),
+            (Cell::Empty, cell) | (cell, Cell::Empty) => cell.clone(),
+            (cell, _) => cell.clone(),
+        }
+    }
+
+    /// Whether the cell holds a number.
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            Cell::Hours(_) | Cell::HoursPair(..) | Cell::Days(_) | Cell::Percent(_)
+        )
+    }
+
+    /// Cell with hours of the kind.
+    pub fn hours(hours: &WorkingHours, kind: HoursKind) -> Self {
+        match kind {
+            HoursKind::Raw => Cell::Hours(hours.raw),
+            HoursKind::Normalized => Cell::Hours(hours.normalized),
+            HoursKind::Both => Cell::HoursPair(hours.normalized, hours.raw),
+        }
+    }
+
+    /// Cell with text, missing if there is no text.
+    pub fn text(text: Option<String>) -> Self {
+        match text {
+            None => Cell::Missing,
+            Some(text) => Cell::Text(text),
+        }
+    }
+
+    /// Text shown for the cell.
+    pub fn display(&self) -> String {
+        match self {
+            Cell::Empty => "".to_owned(),
+            Cell::Missing => "None".to_owned(),
+            Cell::Text(text) => text.clone(),
+            Cell::Hours(hours) => format!("{:.2}", time_ranges::hours(*hours)),
+            Cell::HoursPair(normalized, raw) => format!(
+                "{:.2}/{:.2}",
+                time_ranges::hours(*normalized),
+                time_ranges::hours(*raw)
+            ),
+            Cell::Days(days) => format!("{:.1}", days),
+            Cell::Percent(percent) => format!("{:.1}%", percent),
+        }
+    }
+}
+
+/// Table of a report.
+#[derive(Debug, Clone)]
+pub struct ReportTable {
+    /// Name of the table, used as worksheet name.
+    pub title: String,
+    /// Column headers.
+    pub columns: Vec<String>,
+    /// Cells of the rows, one per column.
+    pub rows: Vec<Vec<Cell>>,
+    /// Totals of the rows, if requested.
+    pub totals: Option<Vec<Cell>>,
+}
+
+/// Report of tracked time in the period.
+#[derive(Debug, Clone)]
+pub struct Report {
+    /// First moment of the report period.
+    pub since: DateTime,
+    /// Last moment of the report period.
+    pub till: DateTime,
+    /// Tables of the report, tasks table first.
+    pub tables: Vec<ReportTable>,
+}
+
+/// What [`build_report`] includes into the report.
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    /// First moment of the report period.
+    pub since: DateTime,
+    /// Last moment of the report period.
+    pub till: DateTime,
+    /// Hours shown in the tasks table.
+    pub hours: HoursKind,
+    /// Add column with task notes from the report period.
+    pub notes: bool,
+    /// Add task and workpackage budget columns.
+    pub budget: bool,
+    /// Add table with absence days per month and kind.
+    pub absences: bool,
+    /// Attribute hours to workpackage valid at the time of work.
+    pub wp_history: bool,
+    /// Add totals row to tables.
+    pub totals: bool,
+    /// Add column with total hours rounded by the rule, month columns keep exact hours.
+    pub rounding: Option<Rounding>,
+}
+
+/// Hours of one task inside the report period.
+pub struct TaskReport {
+    /// Id of the task.
+    pub task_id: String,
+    /// Workpackage valid at the time of work, current task workpackage is used if not set.
+    pub workpackage: Option<Option<String>>,
+    /// Hours tracked in the period.
+    pub total_hours: WorkingHours,
+    /// Total hours rounded if rounding rule is given, month hours are exact.
+    pub rounded_hours: Option<WorkingHours>,
+    /// First and last month with tracked time.
+    pub month_range: (chrono::Month, chrono::Month),
+    /// Hours tracked per month.
+    pub month_hours: HashMap<chrono::Month, WorkingHours>,
+}
+
+/// Computes hours of the task ranges inside the period.
+pub fn task_report(
+    task_id: &str,
+    task_ranges: &[TimeRange],
+    since: DateTime,
+    till: DateTime,
+    calendar: &impl WorkCalendar,
+    clock: &dyn Clock,
+    rounding: Option<&Rounding>,
+) -> TaskReport {
+    let total_hours = time_ranges::working_houres_from_ranges(
+        task_ranges,
+        Some(since),
+        Some(till),
+        calendar,
+        clock,
+    );
+    let rounded_hours = rounding.map(|rounding| {
+        time_ranges::rounded_hours_from_ranges(task_ranges, since, till, calendar, clock, rounding)
+    });
+    let month_hours =
+        time_ranges::month_hours(task_ranges, Some(since), Some(till), calendar, clock);
+    let months_vec: Vec<u32> = month_hours
+        .keys()
+        .copied()
+        .map(|m| m.number_from_month())
+        .collect();
+    let month_range = (
+        *months_vec.iter().min().unwrap(),
+        *months_vec.iter().max().unwrap(),
+    );
+
+    let month_range = (
+        chrono::Month::from_u32(month_range.0).unwrap(),
+        chrono::Month::from_u32(month_range.1).unwrap(),
+    );
+    TaskReport {
+        task_id: task_id.to_owned(),
+        workpackage: None,
+        total_hours,
+        rounded_hours,
+        month_range,
+        month_hours,
+    }
+}
+
+/// Period with the workpackage valid during it.
+pub struct WorkpackagePeriod {
+    /// Start of the period, None since the task creation.
+    pub start: Option<DateTime>,
+    /// End of the period, None till now.
+    pub end: Option<DateTime>,
+    /// Workpackage of the task during the period.
+    pub workpackage: Option<String>,
+}
+
+/// Periods of task workpackages built from task history.
+/// The first period has no start and the last one has no end.
+pub fn workpackage_periods(db: &impl Storage, task_id: &str) -> Result<Vec<WorkpackagePeriod>> {
+    let current = db.get_task(task_id)?.and_then(|t| t.workpackage);
+    let mut periods = Vec::new();
+    let mut start = None;
+    for change in db.get_task_history(task_id)? {
+        if change.field == "workpackage" {
+            periods.push(WorkpackagePeriod {
+                start,
+                end: Some(change.timestamp),
+                workpackage: change.old_value,
+            });
+            start = Some(change.timestamp);
+        }
+    }
+    periods.push(WorkpackagePeriod {
+        start,
+        end: None,
+        workpackage: current,
+    });
+    Ok(periods)
+}
+
+/// Builds reports of all tasks tracked inside the period ordered by task id.
+/// With `wp_history` a task gets separate report per workpackage it belonged to during the period.
+pub fn build_task_reports(
+    db: &impl Storage,
+    since: DateTime,
+    till: DateTime,
+    wp_history: bool,
+    calendar: &impl WorkCalendar,
+    rounding: Option<&Rounding>,
+) -> Result<Vec<TaskReport>> {
+    let ranges = db.select_time_ranges(None, Some(since), Some(till))?;
+    let mut reports = Vec::new();
+    for (task_id, task_ranges) in ranges.iter() {
+        if !wp_history {
+            reports.push(task_report(
+                task_id,
+                task_ranges,
+                since,
+                till,
+                calendar,
+                db.clock(),
+                rounding,
+            ));
+            continue;
+        }
+        for period in workpackage_periods(db, task_id)? {
+            let since = period.start.map_or(since, |s| s.max(since));
+            let till = period.end.map_or(till, |e| e.min(till));
+            let period_ranges: Vec<TimeRange> = task_ranges
+                .iter()
+                .filter(|r| r.start.is_none_or(|s| s < till) && r.end.is_none_or(|e| e > since))
+                .copied()
+                .collect();
+            if since >= till || period_ranges.is_empty() {
+                continue;
+            }
+            let mut report = task_report(
+                task_id,
+                &period_ranges,
+                since,
+                till,
+                calendar,
+                db.clock(),
+                rounding,
+            );
+            report.workpackage = Some(period.workpackage);
+            reports.push(report);
+        }
+    }
+
+    reports.sort_by(|a, b| a.task_id.cmp(&b.task_id));
+    Ok(reports)
+}
+
+/// Task tracked in the report period with its current fields.
+pub struct TaskEntry {
+    /// Hours of the task in the period.
+    pub report: TaskReport,
+    /// Task fields, None if the task does not exist anymore.
+    pub task: Option<Task>,
+}
+
+impl TaskEntry {
+    /// Workpackage the hours are attributed to.
+    pub fn workpackage(&self) -> Option<String> {
+        match &self.report.workpackage {
+            Some(workpackage) => workpackage.clone(),
+            None => self.task.as_ref().and_then(|t| t.workpackage.clone()),
+        }
+    }
+}
+
+/// Tasks tracked in the report period with their totals, base of the tasks table and templates.
+pub struct TasksSummary {
+    /// Months of the report period.
+    pub months: Vec<chrono::Month>,
+    /// Tasks ordered by task id.
+    pub tasks: Vec<TaskEntry>,
+    /// Hours of all tasks.
+    pub total_hours: WorkingHours,
+    /// Rounded hours of all tasks if rounding rule is given.
+    pub rounded_hours: Option<WorkingHours>,
+}
+
+impl TasksSummary {
+    /// Hours of all tasks in the month.
+    pub fn month_hours(&self, month: chrono::Month) -> WorkingHours {
+        self.tasks
+            .iter()
+            .filter_map(|t| t.report.month_hours.get(&month).copied())
+            .sum()
+    }
+
+    /// Percentage of the hours in hours of all tasks, None if nothing is tracked.
+    /// Normalized hours are compared for [`HoursKind::Both`].
+    pub fn share(&self, hours: &WorkingHours, kind: HoursKind) -> Option<f64> {
+        let of_kind = |hours: &WorkingHours| match kind {
+            HoursKind::Raw => hours.raw,
+            HoursKind::Normalized | HoursKind::Both => hours.normalized,
+        };
+        let total = of_kind(&self.total_hours).num_seconds();
+        (total > 0).then(|| of_kind(hours).num_seconds() as f64 / total as f64 * 100.0)
+    }
+}
+
+/// Collects tasks tracked in the report period with their fields and totals.
+pub fn summarize_tasks(db: &impl Storage, options: &ReportOptions) -> Result<TasksSummary> {
+    let calendar = db.work_calendar()?;
+    let reports = build_task_reports(
+        db,
+        options.since,
+        options.till,
+        options.wp_history,
+        &calendar,
+        options.rounding.as_ref(),
+    )?;
+    let total_hours = reports.iter().map(|tr| tr.total_hours).sum();
+    let rounded_hours = options
+        .rounding
+        .map(|_| reports.iter().filter_map(|tr| tr.rounded_hours).sum());
+    let mut tasks = Vec::new();
+    for report in reports {
+        let task = db.get_task(&report.task_id)?;
+        tasks.push(TaskEntry { report, task });
+    }
+    Ok(TasksSummary {
+        months: time_ranges::month_range(options.since, options.till),
+        tasks,
+        total_hours,
+        rounded_hours,
+    })
+}
+
+/// Sums normalized working time ever tracked for the tasks.
+pub fn consumed_time(
+    db: &impl Storage,
+    task_ids: &[String],
+    calendar: &impl WorkCalendar,
+) -> Result<chrono::Duration> {
+    let mut time = chrono::Duration::zero();
+    for task_id in task_ids {
+        if let Some(ranges) = db
+            .select_time_ranges(Some(task_id), None, None)?
+            .get(task_id)
+        {
+            time = time
+                + time_ranges::working_houres_from_ranges(ranges, None, None, calendar, db.clock())
+                    .normalized;
+        }
+    }
+    Ok(time)
+}
+
+/// Row with sums of the summable columns, the first column is labeled `Total`.
+fn totals_row(rows: &[Vec<Cell>], summable: &[bool]) -> Vec<Cell> {
+    let mut totals: Vec<Cell> = summable.iter().map(|_| Cell::Empty).collect();
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            if summable[i] {
+                totals[i] = totals[i].add(cell);
+            }
+        }
+    }
+    totals[0] = Cell::Text("Total".to_owned());
+    totals
+}
+
+/// Builds report of tasks tracked in the period.
+pub fn build_report(db: &impl Storage, options: &ReportOptions) -> Result<Report> {
+    let mut tables = vec![tasks_table(db, options)?];
+    if options.absences {
+        let mut table = absences_table(db, options.since.date_naive(), options.till.date_naive())?;
+        if options.totals {
+            let summable: Vec<bool> = (0..table.columns.len()).map(|i| i > 0).collect();
+            table.totals = Some(totals_row(&table.rows, &summable));
+        }
+        tables.push(table);
+    }
+    Ok(Report {
+        since: options.since,
+        till: options.till,
+        tables,
+    })
+}
+
+/// Builds report for a workbook: tasks, absences per month and the vacations of the period.
+pub fn build_workbook_report(db: &impl Storage, options: &ReportOptions) -> Result<Report> {
+    let options = ReportOptions {
+        absences: true,
+        ..options.clone()
+    };
+    let mut report = build_report(db, &options)?;
+    let vacations = db.list_vacations(options.since, options.till, None)?;
+    report.tables.push(vacations_table(&vacations));
+    Ok(report)
+}
+
+fn tasks_table(db: &impl Storage, options: &ReportOptions) -> Result<ReportTable> {
+    let (since, till) = (options.since, options.till);
+    let calendar = db.work_calendar()?;
+    let summary = summarize_tasks(db, options)?;
+    let month = &summary.months;
+
+    let mut notes: HashMap<String, Vec<String>> = HashMap::new();
+    if options.notes {
+        for note in db.get_notes(None, Some(since), Some(till))? {
+            notes.entry(note.task_id).or_default().push(note.text);
+        }
+    }
+
+    let total_header = match options.hours {
+        HoursKind::Raw => "Tracked hours",
+        HoursKind::Normalized => "Total hours",
+        HoursKind::Both => "Total/tracked hours",
+    };
+    let mut columns: Vec<String> = ["Title", "URL", total_header]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    if options.hours == HoursKind::Both {
+        columns.push("Clipped hours".to_string());
+    }
+    if options.rounding.is_some() {
+        columns.push("Rounded total".to_string());
+    }
+    columns.push("Share".to_string());
+    columns.extend(
+        ["Month range", "Workpackage", "Objective"]
+            .iter()
+            .map(|s| s.to_string()),
+    );
+    if options.budget {
+        columns.extend(
+            [
+                "Budget",
+                "Consumed",
+                "Remaining",
+                "WP budget",
+                "WP remaining",
+            ]
+            .iter()
+            .map(|s| s.to_string()),
+        );
+    }
+    columns.extend(month.iter().map(|m| m.name()[..3].to_string()));
+    if options.notes {
+        columns.push("Notes".to_string());
+    }
+
+    let mut rows = Vec::new();
+    let mut previous = None;
+    for entry in summary.tasks.iter() {
+        let tr = &entry.report;
+        let (title, url, objective, budget) = match entry.task.clone() {
+            Some(task) => (task.title, task.url, task.objective, task.budget),
+            None => (None, None, None, None),
+        };
+        let workpackage = entry.workpackage();
+
+        let mut row = Vec::new();
+        row.push(Cell::Text(format!(
+            "[{}]{}",
+            tr.task_id,
+            title.as_deref().unwrap_or("None")
+        )));
+        row.push(Cell::text(url));
+        row.push(Cell::hours(&tr.total_hours, options.hours));
+        if options.hours == HoursKind::Both {
+            row.push(Cell::Hours(tr.total_hours.clipped()));
+        }
+        if let Some(rounded) = &tr.rounded_hours {
+            row.push(Cell::hours(rounded, options.hours));
+        }
+        match summary.share(&tr.total_hours, options.hours) {
+            Some(share) => row.push(Cell::Percent(share)),
+            None => row.push(Cell::Empty),
+        }
+        let month_range = if tr.month_range.0 == tr.month_range.1 {
+            tr.month_range.0.name()[..3].to_string()
+        } else {
+            format!(
+                "{}-{}",
+                &tr.month_range.0.name()[..3],
+                &tr.month_range.1.name()[..3]
+            )
+        };
+        row.push(Cell::Text(month_range));
+        row.push(Cell::text(workpackage.clone()));
+        row.push(Cell::text(objective));
+        // With workpackage history a task has a row per workpackage, its budget is shown
+        // in the first one only, so totals count it once.
+        let first_row = previous.replace(&tr.task_id) != Some(&tr.task_id);
+        if options.budget {
+            if first_row {
+                let consumed = consumed_time(db, std::slice::from_ref(&tr.task_id), &calendar)?;
+                let budget = budget.map(time_ranges::from_hours);
+                row.push(budget.map(Cell::Hours).unwrap_or(Cell::Empty));
+                row.push(Cell::Hours(consumed));
+                row.push(
+                    budget
+                        .map(|b| Cell::Hours(b - consumed))
+                        .unwrap_or(Cell::Empty),
+                );
+            } else {
+                row.extend([Cell::Empty, Cell::Empty, Cell::Empty]);
+            }
+            let wp_budget = match workpackage.as_ref() {
+                Some(wp) => db
+                    .get_workpackage_budget(wp)?
+                    .map(|b| (wp, time_ranges::from_hours(b))),
+                None => None,
+            };
+            match wp_budget {
+                Some((wp, budget)) => {
+                    let consumed = consumed_time(db, &db.get_workpackage_tasks(wp)?, &calendar)?;
+                    row.push(Cell::Hours(budget));
+                    row.push(Cell::Hours(budget - consumed));
+                }
+                None => {
+                    row.push(Cell::Empty);
+                    row.push(Cell::Empty);
+                }
+            }
+        }
+        for m in month.iter() {
+            match tr.month_hours.get(m) {
+                Some(hours) => row.push(Cell::hours(hours, options.hours)),
+                None => row.push(Cell::Empty),
+            }
+        }
+        if options.notes {
+            row.push(Cell::Text(
+                notes
+                    .get(&tr.task_id)
+                    .map(|n| n.join("; "))
+                    .unwrap_or_default(),
+            ));
+        }
+        rows.push(row);
+    }
+
+    let totals = if options.totals {
+        // Workpackage columns repeat for each task of the workpackage, so they are not summed.
+        let summable: Vec<bool> = columns
+            .iter()
+            .map(|c| {
+                !matches!(
+                    c.as_str(),
+                    "Title"
+                        | "URL"
+                        | "Month range"
+                        | "Workpackage"
+                        | "Objective"
+                        | "WP budget"
+                        | "WP remaining"
+                        | "Notes"
+                )
+            })
+            .collect();
+        Some(totals_row(&rows, &summable))
+    } else {
+        None
+    };
+
+    Ok(ReportTable {
+        title: "Tasks".to_owned(),
+        columns,
+        rows,
+        totals,
+    })
+}
+
+/// Absence days of each kind inside `[since, till]`.
+pub fn absence_days_by_kind(
+    db: &impl Storage,
+    since: NaiveDate,
+    till: NaiveDate,
+) -> Result<HashMap<AbsenceKind, f64>> {
+    let absences = db.list_vacations(
+        time_ranges::from_timestamp(0),
+        time_ranges::from_timestamp(i64::MAX),
+        None,
+    )?;
+    let mut result = HashMap::new();
+    for kind in AbsenceKind::ALL {
+        let ranges = absences
+            .iter()
+            .filter(|a| a.kind == kind)
+            .map(|a| (a.start, a.end))
+            .collect();
+        result.insert(kind, time_ranges::absence_days(ranges, since, till));
+    }
+    Ok(result)
+}
+
+/// Formats absence bound as date, time is added if it is not `day_bound`.
+pub fn format_absence_time(dt: DateTime, day_bound: &str) -> String {
+    let time = dt.format("%H:%M").to_string();
+    if time == day_bound {
+        dt.format("%d.%m.%Y").to_string()
+    } else {
+        format!("{} {}", dt.format("%d.%m.%Y"), time)
+    }
+}
+
+/// Table listing the absences with their id, bounds and kind.
+pub fn vacations_table(absences: &[Absence]) -> ReportTable {
+    let rows = absences
+        .iter()
+        .map(|a| {
+            vec![
+                Cell::Text(a.vacation_id.to_string()),
+                Cell::Text(format_absence_time(a.start, "00:00")),
+                Cell::Text(format_absence_time(a.end, "23:59")),
+                Cell::Text(a.kind.as_str().to_owned()),
+            ]
+        })
+        .collect();
+    ReportTable {
+        title: "Vacations".to_owned(),
+        columns: ["Id", "Start", "End", "Kind"]
+            .iter()
+            .map(|c| c.to_string())
+            .collect(),
+        rows,
+        totals: None,
+    }
+}
+
+/// Table with absence days per month and kind.
+fn absences_table(db: &impl Storage, since: NaiveDate, till: NaiveDate) -> Result<ReportTable> {
+    let mut columns = vec!["Month".to_owned()];
+    columns.extend(AbsenceKind::ALL.iter().map(|k| k.as_str().to_owned()));
+
+    let mut rows = Vec::new();
+    let mut month_start = since;
+    while month_start <= till {
+        let next_month = chronoutil::shift_months(month_start.with_day(1).unwrap(), 1);
+        let month_end = next_month.pred().min(till);
+        let days = absence_days_by_kind(db, month_start, month_end)?;
+        let mut row = vec![Cell::Text(month_start.format("%m.%Y").to_string())];
+        row.extend(AbsenceKind::ALL.iter().map(|k| Cell::Days(days[k])));
+        rows.push(row);
+        month_start = next_month;
+    }
+    Ok(ReportTable {
+        title: "Absences".to_owned(),
+        columns,
+        rows,
+        totals: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        absence_days_by_kind, absences_table, build_report, build_task_reports,
+        build_workbook_report, totals_row, Cell, HoursKind, ReportOptions,
+    };
+    use crate::database::{AbsenceKind, START_VALUE, STOP_VALUE};
+    use crate::storage::{MemoryStorage, Storage};
+    use crate::time_ranges::{morning, Clock, DateTime, Rounding, RoundingMode, RoundingScope};
+    use chrono::{Duration, TimeZone};
+    use std::cell::Cell as TimeCell;
+    use std::rc::Rc;
+
+    /// Clock showing time set by the test.
+    struct SharedClock(Rc<TimeCell<DateTime>>);
+
+    impl Clock for SharedClock {
+        fn now(&self) -> DateTime {
+            self.0.get()
+        }
+    }
+
+    #[test]
+    fn build_task_reports_test() {
+        let dt = |d, h, m| chrono::Utc.ymd(2023, 3, d).and_hms(h, m, 0);
+        let mut storage = MemoryStorage::new();
+        storage.new_task("A", None, None, None, None, None).unwrap();
+        storage.new_task("B", None, None, None, None, None).unwrap();
+        let ranges = [
+            ("A", dt(7, 9, 0), dt(7, 11, 0)),
+            ("B", dt(8, 10, 0), dt(8, 12, 30)),
+            ("A", dt(9, 16, 0), dt(9, 19, 0)),
+        ];
+        for (task_id, start, end) in ranges {
+            storage
+                .update_time_ranges(task_id, START_VALUE, Some(start))
+                .unwrap();
+            storage
+                .update_time_ranges(task_id, STOP_VALUE, Some(end))
+                .unwrap();
+        }
+        storage
+            .add_vacation(dt(8, 0, 0), dt(8, 23, 59), AbsenceKind::Sick)
+            .unwrap();
+
+        let calendar = storage.work_calendar().unwrap();
+        let reports = build_task_reports(
+            &storage,
+            dt(1, 0, 0),
+            dt(31, 23, 59),
+            false,
+            &calendar,
+            None,
+        )
+        .unwrap();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].task_id, "A");
+        assert_eq!(reports[0].total_hours.raw, Duration::hours(5));
+        assert_eq!(reports[0].total_hours.normalized, Duration::hours(3));
+        assert_eq!(reports[1].task_id, "B");
+        assert_eq!(reports[1].total_hours.raw, Duration::minutes(150));
+        assert_eq!(reports[1].total_hours.normalized, Duration::zero());
+    }
+
+    #[test]
+    fn workpackage_history_budget_test() {
+        let dt = |d, h| chrono::Utc.ymd(2023, 3, d).and_hms(h, 0, 0);
+        let now = Rc::new(TimeCell::new(dt(1, 8)));
+        let mut db = MemoryStorage::with_clock(Box::new(SharedClock(now.clone())));
+        db.new_task("A", None, None, Some("WP1"), None, Some(10.0))
+            .unwrap();
+        for (start, end) in [(dt(6, 9), dt(6, 11)), (dt(13, 9), dt(13, 12))] {
+            db.update_time_ranges("A", START_VALUE, Some(start))
+                .unwrap();
+            db.update_time_ranges("A", STOP_VALUE, Some(end)).unwrap();
+        }
+        now.set(dt(10, 8));
+        db.update_task(
+            "A",
+            None,
+            None,
+            Some("WP2"),
+            None,
+            None,
+            false,
+            false,
+            false,
+            false,
+            false,
+        )
+        .unwrap();
+        now.set(dt(31, 23));
+
+        let options = ReportOptions {
+            since: dt(1, 0),
+            till: dt(31, 23),
+            hours: HoursKind::Raw,
+            notes: false,
+            budget: true,
+            absences: false,
+            wp_history: true,
+            totals: true,
+            rounding: None,
+        };
+        let table = build_report(&db, &options).unwrap().tables.remove(0);
+        let column = |name: &str| table.columns.iter().position(|c| c == name).unwrap();
+        let (budget, consumed) = (column("Budget"), column("Consumed"));
+        assert_eq!(table.rows.len(), 2);
+        let workpackage = column("Workpackage");
+        assert_eq!(table.rows[0][workpackage], Cell::Text("WP1".to_owned()));
+        assert_eq!(table.rows[1][workpackage], Cell::Text("WP2".to_owned()));
+        assert_eq!(table.rows[0][budget], Cell::Hours(Duration::hours(10)));
+        assert_eq!(table.rows[0][consumed], Cell::Hours(Duration::hours(5)));
+        assert_eq!(table.rows[1][budget], Cell::Empty);
+        assert_eq!(table.rows[1][consumed], Cell::Empty);
+        let totals = table.totals.unwrap();
+        assert_eq!(totals[budget], Cell::Hours(Duration::hours(10)));
+        assert_eq!(totals[consumed], Cell::Hours(Duration::hours(5)));
+        assert_eq!(
+            totals[column("Tracked hours")],
+            Cell::Hours(Duration::hours(5))
+        );
+    }
+
+    #[test]
+    fn hours_kind_test() {
+        let dt = |d, h| chrono::Utc.ymd(2023, 3, d).and_hms(h, 0, 0);
+        let mut storage =
+            MemoryStorage::with_clock(Box::new(SharedClock(Rc::new(TimeCell::new(dt(31, 23))))));
+        storage.new_task("A", None, None, None, None, None).unwrap();
+        // The last range is on Saturday.
+        for (start, end) in [
+            (dt(7, 9), dt(7, 11)),
+            (dt(9, 16), dt(9, 19)),
+            (dt(11, 10), dt(11, 12)),
+        ] {
+            storage
+                .update_time_ranges("A", START_VALUE, Some(start))
+                .unwrap();
+            storage
+                .update_time_ranges("A", STOP_VALUE, Some(end))
+                .unwrap();
+        }
+
+        let report = |hours| {
+            let options = ReportOptions {
+                since: dt(1, 0),
+                till: dt(31, 23),
+                hours,
+                notes: false,
+                budget: false,
+                absences: false,
+                wp_history: false,
+                totals: false,
+                rounding: None,
+            };
+            build_report(&storage, &options).unwrap().tables.remove(0)
+        };
+        let cell = |table: &super::ReportTable, name: &str| {
+            let column = table.columns.iter().position(|c| c == name).unwrap();
+            table.rows[0][column].clone()
+        };
+
+        let table = report(HoursKind::Raw);
+        assert_eq!(
+            cell(&table, "Tracked hours"),
+            Cell::Hours(Duration::hours(7))
+        );
+        assert_eq!(cell(&table, "Mar"), Cell::Hours(Duration::hours(7)));
+        assert!(!table.columns.iter().any(|c| c == "Clipped hours"));
+        let table = report(HoursKind::Normalized);
+        assert_eq!(cell(&table, "Total hours"), Cell::Hours(Duration::hours(3)));
+        assert_eq!(cell(&table, "Mar"), Cell::Hours(Duration::hours(3)));
+        let table = report(HoursKind::Both);
+        assert_eq!(
+            cell(&table, "Total/tracked hours"),
+            Cell::HoursPair(Duration::hours(3), Duration::hours(7))
+        );
+        assert_eq!(
+            cell(&table, "Clipped hours"),
+            Cell::Hours(Duration::hours(4))
+        );
+        assert_eq!(
+            cell(&table, "Mar"),
+            Cell::HoursPair(Duration::hours(3), Duration::hours(7))
+        );
+    }
+
+    #[test]
+    fn rounded_total_test() {
+        let dt = |d, h, m| chrono::Utc.ymd(2023, 3, d).and_hms(h, m, 0);
+        let mut storage =
+            MemoryStorage::with_clock(Box::new(SharedClock(Rc::new(TimeCell::new(dt(31, 23, 0))))));
+        storage.new_task("A", None, None, None, None, None).unwrap();
+        for (start, end) in [(dt(7, 9, 0), dt(7, 9, 20)), (dt(9, 10, 0), dt(9, 10, 20))] {
+            storage
+                .update_time_ranges("A", START_VALUE, Some(start))
+                .unwrap();
+            storage
+                .update_time_ranges("A", STOP_VALUE, Some(end))
+                .unwrap();
+        }
+        let options = ReportOptions {
+            since: dt(1, 0, 0),
+            till: dt(31, 23, 0),
+            hours: HoursKind::Normalized,
+            notes: false,
+            budget: false,
+            absences: false,
+            wp_history: false,
+            totals: false,
+            rounding: Some(Rounding {
+                increment_minutes: 60,
+                mode: RoundingMode::Up,
+                scope: RoundingScope::Range,
+            }),
+        };
+        let table = build_report(&storage, &options).unwrap().tables.remove(0);
+        let column = |name: &str| table.columns.iter().position(|c| c == name).unwrap();
+        assert_eq!(
+            table.rows[0][column("Rounded total")],
+            Cell::Hours(Duration::hours(2))
+        );
+        assert_eq!(
+            table.rows[0][column("Mar")],
+            Cell::Hours(Duration::minutes(40))
+        );
+    }
+
+    #[test]
+    fn absence_days_by_kind_test() {
+        let date = |m, d| chrono::NaiveDate::from_ymd(2023, m, d);
+        let at = |m, d, h, min| chrono::Utc.ymd(2023, m, d).and_hms(h, min, 0);
+        let storage = MemoryStorage::new();
+        let absences = [
+            (at(3, 6, 0, 0), at(3, 8, 23, 59), AbsenceKind::Vacation),
+            (at(3, 30, 0, 0), at(4, 3, 23, 59), AbsenceKind::Vacation),
+            (
+                morning(date(3, 9)).0,
+                morning(date(3, 9)).1,
+                AbsenceKind::Sick,
+            ),
+            // Weekend days are not counted.
+            (at(3, 11, 0, 0), at(3, 13, 23, 59), AbsenceKind::Training),
+        ];
+        for (start, end, kind) in absences {
+            storage.add_vacation(start, end, kind).unwrap();
+        }
+
+        let days = absence_days_by_kind(&storage, date(3, 1), date(3, 31)).unwrap();
+        assert_eq!(days[&AbsenceKind::Vacation], 5.0);
+        assert_eq!(days[&AbsenceKind::Sick], 0.5);
+        assert_eq!(days[&AbsenceKind::Training], 1.0);
+        assert_eq!(days[&AbsenceKind::Parental], 0.0);
+        assert_eq!(days[&AbsenceKind::Other], 0.0);
+
+        let table = absences_table(&storage, date(3, 15), date(4, 30)).unwrap();
+        assert_eq!(table.columns[1..], AbsenceKind::ALL.map(|k| k.as_str()));
+        let row = |month: &str, days: [f64; 5]| {
+            let mut row = vec![Cell::Text(month.to_owned())];
+            row.extend(days.map(Cell::Days));
+            row
+        };
+        assert_eq!(
+            table.rows,
+            [
+                row("03.2023", [2.0, 0.0, 0.0, 0.0, 0.0]),
+                row("04.2023", [1.0, 0.0, 0.0, 0.0, 0.0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn workbook_report_test() {
+        let at = |m, d, h, min| chrono::Utc.ymd(2023, m, d).and_hms(h, min, 0);
+        let storage = MemoryStorage::new();
+        storage
+            .add_vacation(at(2, 27, 0, 0), at(3, 1, 23, 59), AbsenceKind::Vacation)
+            .unwrap();
+        storage
+            .add_vacation(at(3, 9, 8, 0), at(3, 9, 12, 0), AbsenceKind::Sick)
+            .unwrap();
+        storage
+            .add_vacation(at(4, 3, 0, 0), at(4, 3, 23, 59), AbsenceKind::Vacation)
+            .unwrap();
+
+        let options = ReportOptions {
+            since: at(3, 1, 0, 0),
+            till: at(3, 31, 23, 59),
+            hours: HoursKind::Normalized,
+            notes: false,
+            budget: false,
+            absences: false,
+            wp_history: false,
+            totals: true,
+            rounding: None,
+        };
+        let report = build_workbook_report(&storage, &options).unwrap();
+        let titles: Vec<&str> = report.tables.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, ["Tasks", "Absences", "Vacations"]);
+        let vacations = &report.tables[2];
+        assert_eq!(vacations.columns, ["Id", "Start", "End", "Kind"]);
+        let bounds: Vec<(&Cell, &Cell)> = vacations.rows.iter().map(|r| (&r[1], &r[2])).collect();
+        assert_eq!(
+            bounds,
+            [
+                (
+                    &Cell::Text("27.02.2023".to_owned()),
+                    &Cell::Text("01.03.2023".to_owned())
+                ),
+                (
+                    &Cell::Text("09.03.2023 08:00".to_owned()),
+                    &Cell::Text("09.03.2023 12:00".to_owned())
+                ),
+            ]
+        );
+        assert_eq!(vacations.rows[1][3], Cell::Text("sick".to_owned()));
+    }
+
+    #[test]
+    fn totals_row_test() {
+        let rows = vec![
+            vec![
+                Cell::Text("A".to_owned()),
+                Cell::Hours(Duration::minutes(90)),
+                Cell::HoursPair(Duration::hours(1), Duration::hours(2)),
+                Cell::Percent(25.0),
+                Cell::Text("WP".to_owned()),
+            ],
+            vec![
+                Cell::Text("B".to_owned()),
+                Cell::Empty,
+                Cell::HoursPair(Duration::hours(3), Duration::hours(4)),
+                Cell::Percent(75.0),
+                Cell::Text("WP".to_owned()),
+            ],
+        ];
+        let totals = totals_row(&rows, &[false, true, true, true, false]);
+        assert_eq!(
+            totals,
+            vec![
+                Cell::Text("Total".to_owned()),
+                Cell::Hours(Duration::minutes(90)),
+                Cell::HoursPair(Duration::hours(4), Duration::hours(6)),
+                Cell::Percent(100.0),
+                Cell::Empty,
+            ]
+        );
+    }
+}
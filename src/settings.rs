@@ -0,0 +1,108 @@
+//! Settings stored in the database with their defaults and validation.
+
+use chrono::NaiveDate;
+
+use crate::database::{check_budget, check_days};
+use crate::error::{Error, Result};
+use crate::storage::Storage;
+use crate::time_ranges::{RoundingMode, RoundingScope};
+
+/// Setting known to the application.
+pub struct Setting {
+    /// Name of the setting.
+    pub key: &'static str,
+    /// Meaning of the value shown to the user.
+    pub description: &'static str,
+    /// Value used while the setting is not set.
+    pub default: &'static str,
+    /// Checks whether the value can be stored.
+    pub validate: fn(&str) -> bool,
+}
+
+/// Budget consumption in percent to warn about on activate.
+pub const BUDGET_WARNING: &str = "budget-warning";
+/// Date flex-time balance is counted from.
+pub const BALANCE_START: &str = "balance-start";
+/// Vacation days per year without entitlement of the year.
+pub const VACATION_DAYS: &str = "vacation-days";
+/// Maximum of vacation days carried over to the next year.
+pub const VACATION_CARRY_OVER_MAX: &str = "vacation-carry-over-max";
+/// Minutes report totals are rounded to.
+pub const ROUNDING_INCREMENT: &str = "rounding-increment";
+/// Direction of report hours rounding.
+pub const ROUNDING_MODE: &str = "rounding-mode";
+/// Time rounded at once.
+pub const ROUNDING_SCOPE: &str = "rounding-scope";
+
+/// All known settings.
+pub const SETTINGS: &[Setting] = &[
+    Setting {
+        key: BUDGET_WARNING,
+        description: "Budget consumption in percent to warn about on activate",
+        default: "90",
+        validate: |v| matches!(v.parse(), Ok(v) if check_budget(Some(v)).is_ok()),
+    },
+    Setting {
+        key: BALANCE_START,
+        description: "Date flex-time balance is counted from, first tracked day if empty",
+        default: "",
+        validate: |v| v.is_empty() || parse_date(v).is_some(),
+    },
+    Setting {
+        key: VACATION_DAYS,
+        description: "Vacation days per year if entitlement of the year is not set",
+        default: "26",
+        validate: |v| matches!(v.parse(), Ok(v) if check_days(Some(v)).is_ok()),
+    },
+    Setting {
+        key: VACATION_CARRY_OVER_MAX,
+        description: "Maximum of vacation days carried over to the next year, unlimited if empty",
+        default: "",
+        validate: |v| v.is_empty() || matches!(v.parse(), Ok(v) if check_days(Some(v)).is_ok()),
+    },
+    Setting {
+        key: ROUNDING_INCREMENT,
+        description: "Minutes report totals are rounded to, no rounding if 0",
+        default: "0",
+        validate: |v| v.parse::<u32>().is_ok(),
+    },
+    Setting {
+        key: ROUNDING_MODE,
+        description: "Direction of rounding: up, down or nearest",
+        default: "nearest",
+        validate: |v| RoundingMode::ALL.iter().any(|m| m.as_str() == v),
+    },
+    Setting {
+        key: ROUNDING_SCOPE,
+        description: "Rounded time: every range, day or total of a task",
+        default: "range",
+        validate: |v| RoundingScope::ALL.iter().any(|s| s.as_str() == v),
+    },
+];
+
+/// Setting with the key.
+pub fn find(key: &str) -> Option<&'static Setting> {
+    SETTINGS.iter().find(|s| s.key == key)
+}
+
+/// Parses date in `%d.%m.%Y` format.
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%d.%m.%Y").ok()
+}
+
+/// Value of the setting, default if it is not set. Panics if the key is unknown.
+pub fn get(db: &impl Storage, key: &str) -> Result<String> {
+    let setting = find(key).unwrap();
+    Ok(db
+        .get_setting(key)?
+        .unwrap_or_else(|| setting.default.to_owned()))
+}
+
+/// Value of the setting converted by `parse`, [`Error::InvalidSetting`] if it returns None.
+pub fn get_parsed<T>(
+    db: &impl Storage,
+    key: &str,
+    parse: impl FnOnce(&str) -> Option<T>,
+) -> Result<T> {
+    parse(&get(db, key)?).ok_or_else(|| Error::InvalidSetting(key.to_owned()))
+}
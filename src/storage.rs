@@ -0,0 +1,688 @@
+//! Storage abstraction over tasks, time ranges, notes, absences and settings.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::database::{
+    check_budget, check_days, collect_time_ranges, Absence, AbsenceKind, Database, Note,
+    RangeEvent, Task, TaskChange, TaskStatus, START_VALUE,
+};
+use crate::error::{Error, Result};
+use crate::time_ranges::{
+    from_timestamp, CalendarCombination, Clock, DateTime, SystemClock, TimeRange,
+};
+
+/// Tasks, their time ranges, notes, absences and settings. [`Database`] stores them
+/// in SQLite, [`MemoryStorage`] keeps them in memory for tests.
+pub trait Storage {
+    /// Clock used for timestamps of changes and as end of open ranges.
+    fn clock(&self) -> &dyn Clock;
+
+    /// Returns tasks ordered by last update, the most recent first.
+    /// Only id, title and status of tasks are guaranteed to be filled.
+    fn list_tasks(&self, top_n: Option<usize>, with_archived: bool) -> Result<Vec<Task>>;
+
+    /// Returns task by id.
+    fn get_task(&self, task_id: &str) -> Result<Option<Task>>;
+
+    /// Creates new task.
+    fn new_task(
+        &self,
+        task_id: &str,
+        url: Option<&str>,
+        title: Option<&str>,
+        wp: Option<&str>,
+        o: Option<&str>,
+        budget: Option<f64>,
+    ) -> Result<()>;
+
+    /// Updates or drops task fields. Returns whether the task was found and whether anything was requested to change.
+    #[allow(clippy::too_many_arguments)]
+    fn update_task(
+        &self,
+        task_id: &str,
+        url: Option<&str>,
+        title: Option<&str>,
+        wp: Option<&str>,
+        o: Option<&str>,
+        budget: Option<f64>,
+        drop_url: bool,
+        drop_title: bool,
+        drop_wp: bool,
+        drop_o: bool,
+        drop_budget: bool,
+    ) -> Result<(bool, bool)>;
+
+    /// Returns changes of task fields ordered by time.
+    fn get_task_history(&self, task_id: &str) -> Result<Vec<TaskChange>>;
+
+    /// Returns ids of all tasks in workpackage.
+    fn get_workpackage_tasks(&self, workpackage: &str) -> Result<Vec<String>>;
+
+    /// Returns budget of the workpackage in hours.
+    fn get_workpackage_budget(&self, workpackage: &str) -> Result<Option<f64>>;
+
+    /// Sets or drops budget of the workpackage.
+    fn set_workpackage_budget(&self, workpackage: &str, budget: Option<f64>) -> Result<()>;
+
+    /// Returns value of the setting if it was set.
+    fn get_setting(&self, key: &str) -> Result<Option<String>>;
+
+    /// Sets setting value, `None` removes setting.
+    fn set_setting(&self, key: &str, value: Option<&str>) -> Result<()>;
+
+    /// Adds note to the task, optionally attached to the range started at `range_start`.
+    fn add_note(&self, task_id: &str, text: &str, range_start: Option<DateTime>) -> Result<i64>;
+
+    /// Returns notes ordered by creation time. All filters are optional.
+    fn get_notes(
+        &self,
+        task_id: Option<&str>,
+        start_date: Option<DateTime>,
+        end_date: Option<DateTime>,
+    ) -> Result<Vec<Note>>;
+
+    /// Returns id of the task with not stopped time range.
+    fn get_current_task_id(&self) -> Result<Option<String>>;
+
+    /// Adds start or stop record of the task at `dt` or now.
+    fn update_time_ranges(&mut self, task_id: &str, value: i64, dt: Option<DateTime>)
+        -> Result<()>;
+
+    /// Returns time ranges per task built from records of the task within the period.
+    /// All filters are optional.
+    fn select_time_ranges(
+        &self,
+        task_id: Option<&str>,
+        start_date: Option<DateTime>,
+        end_date: Option<DateTime>,
+    ) -> Result<HashMap<String, Vec<TimeRange>>>;
+
+    /// Returns all time range records ordered by time, stops before starts of the same time.
+    fn get_range_events(&self) -> Result<Vec<RangeEvent>>;
+
+    /// Returns time of the earliest time range record.
+    fn get_first_range_time(&self) -> Result<Option<DateTime>>;
+
+    /// Adds absence from start till end.
+    fn add_vacation(&self, start: DateTime, end: DateTime, kind: AbsenceKind) -> Result<()>;
+
+    /// Deletes absence by id.
+    fn delete_vacation(&self, vacation_id: i64) -> Result<()>;
+
+    /// Returns time ranges of all absences.
+    fn get_vacations(&self) -> Result<Vec<(DateTime, DateTime)>>;
+
+    /// Returns absences overlapping `[start, end]`, all kinds if `kind` is not set.
+    fn list_vacations(
+        &self,
+        start: DateTime,
+        end: DateTime,
+        kind: Option<AbsenceKind>,
+    ) -> Result<Vec<Absence>>;
+
+    /// Sets vacation days of the year, carry-over is computed from the previous
+    /// year if not set.
+    fn set_vacation_entitlement(&self, year: i32, days: f64, carry_over: Option<f64>)
+        -> Result<()>;
+
+    /// Returns vacation days and carry-over days of the year.
+    fn get_vacation_entitlement(&self, year: i32) -> Result<Option<(f64, Option<f64>)>>;
+
+    /// Holiday calendar including all absences.
+    fn work_calendar(&self) -> Result<CalendarCombination>;
+}
+
+impl Storage for Database {
+    fn clock(&self) -> &dyn Clock {
+        Database::clock(self)
+    }
+
+    fn list_tasks(&self, top_n: Option<usize>, with_archived: bool) -> Result<Vec<Task>> {
+        Database::list_tasks(self, top_n, with_archived)
+    }
+
+    fn get_task(&self, task_id: &str) -> Result<Option<Task>> {
+        Database::get_task(self, task_id)
+    }
+
+    fn new_task(
+        &self,
+        task_id: &str,
+        url: Option<&str>,
+        title: Option<&str>,
+        wp: Option<&str>,
+        o: Option<&str>,
+        budget: Option<f64>,
+    ) -> Result<()> {
+        Database::new_task(self, task_id, url, title, wp, o, budget)
+    }
+
+    fn update_task(
+        &self,
+        task_id: &str,
+        url: Option<&str>,
+        title: Option<&str>,
+        wp: Option<&str>,
+        o: Option<&str>,
+        budget: Option<f64>,
+        drop_url: bool,
+        drop_title: bool,
+        drop_wp: bool,
+        drop_o: bool,
+        drop_budget: bool,
+    ) -> Result<(bool, bool)> {
+        Database::update_task(
+            self,
+            task_id,
+            url,
+            title,
+            wp,
+            o,
+            budget,
+            drop_url,
+            drop_title,
+            drop_wp,
+            drop_o,
+            drop_budget,
+        )
+    }
+
+    fn get_task_history(&self, task_id: &str) -> Result<Vec<TaskChange>> {
+        Database::get_task_history(self, task_id)
+    }
+
+    fn get_workpackage_tasks(&self, workpackage: &str) -> Result<Vec<String>> {
+        Database::get_workpackage_tasks(self, workpackage)
+    }
+
+    fn get_workpackage_budget(&self, workpackage: &str) -> Result<Option<f64>> {
+        Database::get_workpackage_budget(self, workpackage)
+    }
+
+    fn set_workpackage_budget(&self, workpackage: &str, budget: Option<f64>) -> Result<()> {
+        Database::set_workpackage_budget(self, workpackage, budget)
+    }
+
+    fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        Database::get_setting(self, key)
+    }
+
+    fn set_setting(&self, key: &str, value: Option<&str>) -> Result<()> {
+        Database::set_setting(self, key, value)
+    }
+
+    fn add_note(&self, task_id: &str, text: &str, range_start: Option<DateTime>) -> Result<i64> {
+        Database::add_note(self, task_id, text, range_start)
+    }
+
+    fn get_notes(
+        &self,
+        task_id: Option<&str>,
+        start_date: Option<DateTime>,
+        end_date: Option<DateTime>,
+    ) -> Result<Vec<Note>> {
+        Database::get_notes(self, task_id, start_date, end_date)
+    }
+
+    fn get_current_task_id(&self) -> Result<Option<String>> {
+        Database::get_current_task_id(self)
+    }
+
+    fn update_time_ranges(
+        &mut self,
+        task_id: &str,
+        value: i64,
+        dt: Option<DateTime>,
+    ) -> Result<()> {
+        Database::update_time_ranges(self, task_id, value, dt)
+    }
+
+    fn select_time_ranges(
+        &self,
+        task_id: Option<&str>,
+        start_date: Option<DateTime>,
+        end_date: Option<DateTime>,
+    ) -> Result<HashMap<String, Vec<TimeRange>>> {
+        Database::select_time_ranges(self, task_id, start_date, end_date)
+    }
+
+    fn get_range_events(&self) -> Result<Vec<RangeEvent>> {
+        Database::get_range_events(self)
+    }
+
+    fn get_first_range_time(&self) -> Result<Option<DateTime>> {
+        Database::get_first_range_time(self)
+    }
+
+    fn add_vacation(&self, start: DateTime, end: DateTime, kind: AbsenceKind) -> Result<()> {
+        Database::add_vacation(self, start, end, kind)
+    }
+
+    fn delete_vacation(&self, vacation_id: i64) -> Result<()> {
+        Database::delete_vacation(self, vacation_id)
+    }
+
+    fn get_vacations(&self) -> Result<Vec<(DateTime, DateTime)>> {
+        Database::get_vacations(self)
+    }
+
+    fn list_vacations(
+        &self,
+        start: DateTime,
+        end: DateTime,
+        kind: Option<AbsenceKind>,
+    ) -> Result<Vec<Absence>> {
+        Database::list_vacations(self, start, end, kind)
+    }
+
+    fn set_vacation_entitlement(
+        &self,
+        year: i32,
+        days: f64,
+        carry_over: Option<f64>,
+    ) -> Result<()> {
+        Database::set_vacation_entitlement(self, year, days, carry_over)
+    }
+
+    fn get_vacation_entitlement(&self, year: i32) -> Result<Option<(f64, Option<f64>)>> {
+        Database::get_vacation_entitlement(self, year)
+    }
+
+    fn work_calendar(&self) -> Result<CalendarCombination> {
+        Database::work_calendar(self)
+    }
+}
+
+#[derive(Default)]
+struct MemoryData {
+    /// Tasks with their last update timestamps.
+    tasks: Vec<(Task, i64)>,
+    history: Vec<(String, TaskChange)>,
+    events: Vec<RangeEvent>,
+    notes: Vec<Note>,
+    vacations: Vec<Absence>,
+    entitlements: HashMap<i32, (f64, Option<f64>)>,
+    workpackage_budgets: HashMap<String, f64>,
+    settings: HashMap<String, String>,
+    last_id: i64,
+}
+
+impl MemoryData {
+    fn next_id(&mut self) -> i64 {
+        self.last_id += 1;
+        self.last_id
+    }
+}
+
+/// Storage keeping all data in memory, nothing is persisted.
+pub struct MemoryStorage {
+    data: RefCell<MemoryData>,
+    clock: Box<dyn Clock>,
+}
+
+impl Default for MemoryStorage {
+    fn default() -> Self {
+        Self::with_clock(Box::new(SystemClock))
+    }
+}
+
+impl MemoryStorage {
+    /// Creates empty storage.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates empty storage taking the current time from `clock`.
+    pub fn with_clock(clock: Box<dyn Clock>) -> Self {
+        Self {
+            data: RefCell::new(MemoryData::default()),
+            clock,
+        }
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    fn list_tasks(&self, top_n: Option<usize>, with_archived: bool) -> Result<Vec<Task>> {
+        let data = self.data.borrow();
+        let mut tasks: Vec<&(Task, i64)> = data
+            .tasks
+            .iter()
+            .filter(|(t, _)| with_archived || t.status != TaskStatus::Archived)
+            .collect();
+        tasks.sort_by_key(|(_, last_update)| std::cmp::Reverse(*last_update));
+        Ok(tasks
+            .into_iter()
+            .take(top_n.unwrap_or(usize::MAX))
+            .map(|(t, _)| t.clone())
+            .collect())
+    }
+
+    fn get_task(&self, task_id: &str) -> Result<Option<Task>> {
+        Ok(self
+            .data
+            .borrow()
+            .tasks
+            .iter()
+            .find(|(t, _)| t.task_id == task_id)
+            .map(|(t, _)| t.clone()))
+    }
+
+    fn new_task(
+        &self,
+        task_id: &str,
+        url: Option<&str>,
+        title: Option<&str>,
+        wp: Option<&str>,
+        o: Option<&str>,
+        budget: Option<f64>,
+    ) -> Result<()> {
+        check_budget(budget)?;
+        if self.get_task(task_id)?.is_some() {
+            return Err(Error::TaskExists);
+        }
+        let task = Task {
+            task_id: task_id.to_owned(),
+            url: url.map(str::to_owned),
+            title: title.map(str::to_owned),
+            workpackage: wp.map(str::to_owned),
+            objective: o.map(str::to_owned),
+            status: TaskStatus::Open,
+            budget,
+        };
+        let now = self.clock.now_timestamp();
+        self.data.borrow_mut().tasks.push((task, now));
+        Ok(())
+    }
+
+    fn update_task(
+        &self,
+        task_id: &str,
+        url: Option<&str>,
+        title: Option<&str>,
+        wp: Option<&str>,
+        o: Option<&str>,
+        budget: Option<f64>,
+        drop_url: bool,
+        drop_title: bool,
+        drop_wp: bool,
+        drop_o: bool,
+        drop_budget: bool,
+    ) -> Result<(bool, bool)> {
+        check_budget(budget)?;
+        let budget = budget.map(|b| format!("{:?}", b));
+        let changes = [
+            ("url", url, drop_url),
+            ("title", title, drop_title),
+            ("workpackage", wp, drop_wp),
+            ("objective", o, drop_o),
+            ("budget", budget.as_deref(), drop_budget),
+        ];
+        let requested = changes.iter().any(|(_, v, drop)| v.is_some() || *drop);
+        let now = self.clock.now_timestamp();
+        let mut data = self.data.borrow_mut();
+        let data = &mut *data;
+        let (task, last_update) = match data.tasks.iter_mut().find(|(t, _)| t.task_id == task_id) {
+            Some(entry) => entry,
+            None => return Ok((false, requested)),
+        };
+        for (field, value, drop) in changes {
+            if value.is_none() && !drop {
+                continue;
+            }
+            let value = if drop { None } else { value.map(str::to_owned) };
+            let old_value = match field {
+                "url" => std::mem::replace(&mut task.url, value.clone()),
+                "title" => std::mem::replace(&mut task.title, value.clone()),
+                "workpackage" => std::mem::replace(&mut task.workpackage, value.clone()),
+                "objective" => std::mem::replace(&mut task.objective, value.clone()),
+                _ => {
+                    std::mem::replace(&mut task.budget, value.as_ref().map(|b| b.parse().unwrap()))
+                        .map(|b| format!("{:?}", b))
+                }
+            };
+            *last_update = now;
+            if old_value != value {
+                data.history.push((
+                    task_id.to_owned(),
+                    TaskChange {
+                        field: field.to_owned(),
+                        old_value,
+                        new_value: value,
+                        timestamp: from_timestamp(now),
+                    },
+                ));
+            }
+        }
+        Ok((requested, requested))
+    }
+
+    fn get_task_history(&self, task_id: &str) -> Result<Vec<TaskChange>> {
+        let mut changes: Vec<TaskChange> = self
+            .data
+            .borrow()
+            .history
+            .iter()
+            .filter(|(t, _)| t == task_id)
+            .map(|(_, c)| c.clone())
+            .collect();
+        changes.sort_by_key(|c| c.timestamp);
+        Ok(changes)
+    }
+
+    fn get_workpackage_tasks(&self, workpackage: &str) -> Result<Vec<String>> {
+        Ok(self
+            .data
+            .borrow()
+            .tasks
+            .iter()
+            .filter(|(t, _)| t.workpackage.as_deref() == Some(workpackage))
+            .map(|(t, _)| t.task_id.clone())
+            .collect())
+    }
+
+    fn get_workpackage_budget(&self, workpackage: &str) -> Result<Option<f64>> {
+        Ok(self
+            .data
+            .borrow()
+            .workpackage_budgets
+            .get(workpackage)
+            .copied())
+    }
+
+    fn set_workpackage_budget(&self, workpackage: &str, budget: Option<f64>) -> Result<()> {
+        check_budget(budget)?;
+        let budgets = &mut self.data.borrow_mut().workpackage_budgets;
+        match budget {
+            Some(budget) => budgets.insert(workpackage.to_owned(), budget),
+            None => budgets.remove(workpackage),
+        };
+        Ok(())
+    }
+
+    fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.data.borrow().settings.get(key).cloned())
+    }
+
+    fn set_setting(&self, key: &str, value: Option<&str>) -> Result<()> {
+        let settings = &mut self.data.borrow_mut().settings;
+        match value {
+            Some(value) => settings.insert(key.to_owned(), value.to_owned()),
+            None => settings.remove(key),
+        };
+        Ok(())
+    }
+
+    fn add_note(&self, task_id: &str, text: &str, range_start: Option<DateTime>) -> Result<i64> {
+        let timestamp = self.clock.now();
+        let mut data = self.data.borrow_mut();
+        let note_id = data.next_id();
+        data.notes.push(Note {
+            note_id,
+            task_id: task_id.to_owned(),
+            timestamp,
+            range_start,
+            text: text.to_owned(),
+        });
+        Ok(note_id)
+    }
+
+    fn get_notes(
+        &self,
+        task_id: Option<&str>,
+        start_date: Option<DateTime>,
+        end_date: Option<DateTime>,
+    ) -> Result<Vec<Note>> {
+        let mut notes: Vec<Note> = self
+            .data
+            .borrow()
+            .notes
+            .iter()
+            .filter(|n| task_id.is_none_or(|t| n.task_id == t))
+            .filter(|n| start_date.is_none_or(|s| n.timestamp >= s))
+            .filter(|n| end_date.is_none_or(|e| n.timestamp <= e))
+            .cloned()
+            .collect();
+        notes.sort_by_key(|n| n.timestamp);
+        Ok(notes)
+    }
+
+    fn get_current_task_id(&self) -> Result<Option<String>> {
+        Ok(self
+            .data
+            .borrow()
+            .events
+            .iter()
+            .max_by_key(|e| (e.timestamp, e.rowid))
+            .filter(|e| e.start_or_stop == START_VALUE)
+            .map(|e| e.task_id.clone()))
+    }
+
+    fn update_time_ranges(
+        &mut self,
+        task_id: &str,
+        value: i64,
+        dt: Option<DateTime>,
+    ) -> Result<()> {
+        let now = self.clock.now_timestamp();
+        let data = self.data.get_mut();
+        match data.tasks.iter_mut().find(|(t, _)| t.task_id == task_id) {
+            None => return Err(Error::RangesUpdate),
+            Some((_, last_update)) => *last_update = now,
+        }
+        let rowid = data.next_id();
+        data.events.push(RangeEvent {
+            rowid,
+            task_id: task_id.to_owned(),
+            timestamp: dt.unwrap_or_else(|| from_timestamp(now)),
+            start_or_stop: value,
+        });
+        Ok(())
+    }
+
+    fn select_time_ranges(
+        &self,
+        task_id: Option<&str>,
+        start_date: Option<DateTime>,
+        end_date: Option<DateTime>,
+    ) -> Result<HashMap<String, Vec<TimeRange>>> {
+        Ok(collect_time_ranges(
+            self.get_range_events()?
+                .into_iter()
+                .filter(|e| task_id.is_none_or(|t| e.task_id == t))
+                .filter(|e| start_date.is_none_or(|s| e.timestamp >= s))
+                .filter(|e| end_date.is_none_or(|s| e.timestamp <= s))
+                .map(|e| (e.task_id, e.timestamp, e.start_or_stop)),
+        ))
+    }
+
+    fn get_range_events(&self) -> Result<Vec<RangeEvent>> {
+        let mut events = self.data.borrow().events.clone();
+        events.sort_by_key(|e| (e.timestamp, e.start_or_stop, e.rowid));
+        Ok(events)
+    }
+
+    fn get_first_range_time(&self) -> Result<Option<DateTime>> {
+        Ok(self.data.borrow().events.iter().map(|e| e.timestamp).min())
+    }
+
+    fn add_vacation(&self, start: DateTime, end: DateTime, kind: AbsenceKind) -> Result<()> {
+        if start > end {
+            return Err(Error::InvalidRange);
+        }
+        let mut data = self.data.borrow_mut();
+        let vacation_id = data.next_id();
+        data.vacations.push(Absence {
+            vacation_id,
+            start,
+            end,
+            kind,
+        });
+        Ok(())
+    }
+
+    fn delete_vacation(&self, vacation_id: i64) -> Result<()> {
+        self.data
+            .borrow_mut()
+            .vacations
+            .retain(|v| v.vacation_id != vacation_id);
+        Ok(())
+    }
+
+    fn get_vacations(&self) -> Result<Vec<(DateTime, DateTime)>> {
+        Ok(self
+            .data
+            .borrow()
+            .vacations
+            .iter()
+            .map(|v| (v.start, v.end))
+            .collect())
+    }
+
+    fn list_vacations(
+        &self,
+        start: DateTime,
+        end: DateTime,
+        kind: Option<AbsenceKind>,
+    ) -> Result<Vec<Absence>> {
+        let mut vacations: Vec<Absence> = self
+            .data
+            .borrow()
+            .vacations
+            .iter()
+            .filter(|v| v.start <= end && v.end >= start && kind.is_none_or(|k| v.kind == k))
+            .cloned()
+            .collect();
+        vacations.sort_by_key(|v| v.start);
+        Ok(vacations)
+    }
+
+    fn set_vacation_entitlement(
+        &self,
+        year: i32,
+        days: f64,
+        carry_over: Option<f64>,
+    ) -> Result<()> {
+        check_days(Some(days))?;
+        check_days(carry_over)?;
+        self.data
+            .borrow_mut()
+            .entitlements
+            .insert(year, (days, carry_over));
+        Ok(())
+    }
+
+    fn get_vacation_entitlement(&self, year: i32) -> Result<Option<(f64, Option<f64>)>> {
+        Ok(self.data.borrow().entitlements.get(&year).copied())
+    }
+
+    fn work_calendar(&self) -> Result<CalendarCombination> {
+        Ok(CalendarCombination::holydays_and_vacations(
+            self.get_vacations()?,
+        ))
+    }
+}
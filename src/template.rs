@@ -0,0 +1,192 @@
+//! Reports rendered through [Tera](https://keats.github.io/tera/) templates.
+//!
+//! Templates get the data built by [`template_data`]:
+//! `since`, `till`, `generated`, `months`, `tasks`, `totals`, `vacations` and `absence_days`.
+
+use serde_json::{json, Value};
+
+use crate::database::AbsenceKind;
+use crate::error::{Error, Result};
+use crate::report::{self, HoursKind, ReportOptions};
+use crate::storage::Storage;
+use crate::time_ranges::hours;
+
+/// Templates shipped with the binary, file name and source.
+pub const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    (
+        "timesheet.txt",
+        include_str!("../templates/timesheet.txt.tera"),
+    ),
+    (
+        "timesheet.md",
+        include_str!("../templates/timesheet.md.tera"),
+    ),
+    (
+        "timesheet.html",
+        include_str!("../templates/timesheet.html.tera"),
+    ),
+];
+
+/// Source of built-in template by name.
+pub fn builtin_template(name: &str) -> Option<&'static str> {
+    BUILTIN_TEMPLATES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, source)| *source)
+}
+
+const DATE_FORMAT: &str = "%d.%m.%Y";
+
+/// Report data passed to templates, built from [`report::summarize_tasks`].
+///
+/// Hours of `tasks` and `totals` are normalized, `raw_hours` are tracked wall-clock hours.
+/// `share` of a task is its percentage of the total hours. `rounded_hours` are null
+/// without rounding rule, `month_hours` are never rounded.
+/// `month_hours` follow the order of `months`.
+pub fn template_data(db: &impl Storage, options: &ReportOptions) -> Result<Value> {
+    let (since, till) = (options.since, options.till);
+    let summary = report::summarize_tasks(db, options)?;
+    let months = &summary.months;
+
+    let mut tasks = Vec::new();
+    for entry in summary.tasks.iter() {
+        let (tr, task) = (&entry.report, entry.task.as_ref());
+        let month_hours: Vec<f64> = months
+            .iter()
+            .map(|m| tr.month_hours.get(m).map_or(0.0, |h| hours(h.normalized)))
+            .collect();
+        tasks.push(json!({
+            "id": tr.task_id,
+            "title": task.and_then(|t| t.title.clone()),
+            "url": task.and_then(|t| t.url.clone()),
+            "workpackage": entry.workpackage(),
+            "objective": task.and_then(|t| t.objective.clone()),
+            "status": task.map(|t| t.status.as_str()),
+            "budget": task.and_then(|t| t.budget),
+            "hours": hours(tr.total_hours.normalized),
+            "raw_hours": hours(tr.total_hours.raw),
+            "rounded_hours": tr.rounded_hours.map(|h| hours(h.normalized)),
+            "share": summary
+                .share(&tr.total_hours, HoursKind::Normalized)
+                .unwrap_or(0.0),
+            "month_hours": month_hours,
+        }));
+    }
+
+    let vacations: Vec<Value> = db
+        .list_vacations(since, till, None)?
+        .iter()
+        .map(|a| {
+            json!({
+                "kind": a.kind.as_str(),
+                "start": a.start.format(DATE_FORMAT).to_string(),
+                "end": a.end.format(DATE_FORMAT).to_string(),
+            })
+        })
+        .collect();
+    let days = report::absence_days_by_kind(db, since.date_naive(), till.date_naive())?;
+    let absence_days: serde_json::Map<String, Value> = AbsenceKind::ALL
+        .iter()
+        .map(|k| (k.as_str().to_owned(), json!(days[k])))
+        .collect();
+
+    Ok(json!({
+        "since": since.format(DATE_FORMAT).to_string(),
+        "till": till.format(DATE_FORMAT).to_string(),
+        "generated": db.clock().now().format("%d.%m.%Y %H:%M").to_string(),
+        "months": months
+            .iter()
+            .map(|m| json!({"name": m.name(), "short": &m.name()[..3]}))
+            .collect::<Vec<_>>(),
+        "tasks": tasks,
+        "totals": {
+            "hours": hours(summary.total_hours.normalized),
+            "raw_hours": hours(summary.total_hours.raw),
+            "rounded_hours": summary.rounded_hours.map(|h| hours(h.normalized)),
+            "month_hours": months
+                .iter()
+                .map(|m| hours(summary.month_hours(*m).normalized))
+                .collect::<Vec<_>>(),
+        },
+        "vacations": vacations,
+        "absence_days": absence_days,
+    }))
+}
+
+/// Renders template source with the data, HTML and XML templates are escaped by `name`.
+pub fn render_template(name: &str, source: &str, data: &Value) -> Result<String> {
+    let context = tera::Context::from_value(data.clone())?;
+    let autoescape = [".html", ".htm", ".xml"]
+        .iter()
+        .any(|ext| name.trim_end_matches(".tera").ends_with(ext));
+    tera::Tera::one_off(source, &context, autoescape).map_err(Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_template, template_data, BUILTIN_TEMPLATES};
+    use crate::database::{START_VALUE, STOP_VALUE};
+    use crate::report::{HoursKind, ReportOptions};
+    use crate::storage::{MemoryStorage, Storage};
+    use chrono::TimeZone;
+
+    #[test]
+    fn builtin_templates_test() {
+        let dt = |d, h| chrono::Utc.ymd(2023, 3, d).and_hms(h, 0, 0);
+        let mut storage = MemoryStorage::new();
+        storage
+            .new_task(
+                "A",
+                Some("https://a?x=1&y=2"),
+                Some("<A&B>"),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        storage
+            .new_task(
+                "B",
+                Some("https://b?x=1&y=2"),
+                None,
+                Some("W|P"),
+                None,
+                None,
+            )
+            .unwrap();
+        for (task_id, day) in [("A", 7), ("B", 8)] {
+            storage
+                .update_time_ranges(task_id, START_VALUE, Some(dt(day, 9)))
+                .unwrap();
+            storage
+                .update_time_ranges(task_id, STOP_VALUE, Some(dt(day, 10)))
+                .unwrap();
+        }
+        let options = ReportOptions {
+            since: dt(1, 0),
+            till: dt(31, 23),
+            hours: HoursKind::Normalized,
+            notes: false,
+            budget: false,
+            absences: false,
+            wp_history: false,
+            totals: true,
+            rounding: None,
+        };
+        let data = template_data(&storage, &options).unwrap();
+        assert_eq!(data["totals"]["hours"], 2.0);
+        assert_eq!(data["totals"]["month_hours"][0], 2.0);
+        assert_eq!(data["tasks"][1]["share"], 50.0);
+
+        for (name, source) in BUILTIN_TEMPLATES {
+            let text = render_template(name, source, &data).unwrap();
+            assert!(text.contains("01.03.2023"), "{}", name);
+        }
+        let html = render_template("timesheet.html", BUILTIN_TEMPLATES[2].1, &data).unwrap();
+        assert!(html.contains("\">&lt;A&amp;B&gt;</a>"));
+        assert!(html.contains(">https:&#x2F;&#x2F;b?x=1&amp;y=2</a>"));
+        assert!(!html.contains("&amp;amp;"));
+        let markdown = render_template("timesheet.md", BUILTIN_TEMPLATES[1].1, &data).unwrap();
+        assert!(markdown.contains("| B |  | W\\|P |"));
+    }
+}
@@ -1,9 +1,12 @@
-use std::collections::HashMap;
+//! Time ranges built from records and working hours computed from them.
+
+use std::collections::{BTreeMap, HashMap};
 
 use bdays::HolidayCalendar;
 use chrono::{Datelike, NaiveDate, TimeZone};
 use num_traits::FromPrimitive;
 
+/// Point in time used for all computations.
 pub type DateTime = chrono::DateTime<chrono::Utc>;
 
 struct LuxembourgHolidayCalender;
@@ -11,32 +14,61 @@ struct VacationsCalendar {
     vacations: Vec<(DateTime, DateTime)>,
 }
 
+/// Holiday calendar which knows about absences shorter than a day.
+pub trait WorkCalendar: HolidayCalendar<DateTime> {
+    /// Absence time during working time of the date.
+    fn absence_time(&self, _date: NaiveDate) -> chrono::Duration {
+        chrono::Duration::zero()
+    }
+}
+
+impl WorkCalendar for bdays::calendars::WeekendsOnly {}
+
+/// Public holidays combined with absences.
 pub struct CalendarCombination {
     calendars: Vec<Box<dyn HolidayCalendar<DateTime>>>,
+    vacations: VacationsCalendar,
 }
 
 impl CalendarCombination {
+    /// Weekends and Luxembourg public holidays combined with the absences.
     pub fn holydays_and_vacations(vacations: Vec<(DateTime, DateTime)>) -> Self {
         Self {
-            calendars: vec![
-                Box::new(LuxembourgHolidayCalender),
-                Box::new(VacationsCalendar { vacations }),
-            ],
+            calendars: vec![Box::new(LuxembourgHolidayCalender)],
+            vacations: VacationsCalendar { vacations },
         }
     }
 }
 
 impl HolidayCalendar<DateTime> for CalendarCombination {
     fn is_holiday(&self, date: DateTime) -> bool {
-        self.calendars.iter().any(|c| c.is_holiday(date))
+        self.calendars.iter().any(|c| c.is_holiday(date)) || self.vacations.is_holiday(date)
+    }
+}
+
+impl WorkCalendar for CalendarCombination {
+    fn absence_time(&self, date: NaiveDate) -> chrono::Duration {
+        self.vacations.absence_time(date)
     }
 }
 
+/// Day is a holiday only if vacations cover all its working time.
 impl HolidayCalendar<DateTime> for VacationsCalendar {
     fn is_holiday(&self, date: DateTime) -> bool {
+        self.absence_time(date.date_naive()) >= work_day()
+    }
+}
+
+impl WorkCalendar for VacationsCalendar {
+    fn absence_time(&self, date: NaiveDate) -> chrono::Duration {
+        let day_begin = DateTime::from_utc(date.and_hms(0, 0, 0), chrono::Utc);
+        let day_finish = DateTime::from_utc(date.and_hms(23, 59, 59), chrono::Utc);
         self.vacations
             .iter()
-            .any(|(start, end)| date >= *start && date <= *end)
+            .map(|(start, end)| ((*start).max(day_begin), (*end).min(day_finish)))
+            .filter(|(start, end)| start < end)
+            .map(|(start, end)| count_hours(start.time(), end.time()))
+            .fold(chrono::Duration::zero(), |a, b| a + b)
     }
 }
 
@@ -81,75 +113,169 @@ impl HolidayCalendar<DateTime> for LuxembourgHolidayCalender {
     }
 }
 
-#[derive(Debug)]
+/// Hours of the duration, for display only.
+pub fn hours(duration: chrono::Duration) -> f64 {
+    duration.num_seconds() as f64 / 3600.0
+}
+
+/// Duration of hours given by user, e.g. budget, in whole seconds.
+pub fn from_hours(hours: f64) -> chrono::Duration {
+    chrono::Duration::seconds((hours * 3600.0).round() as i64)
+}
+
+/// Tracked time of ranges in whole seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkingHours {
+    /// Wall-clock duration
+    pub raw: chrono::Duration,
+    /// Time inside working days and working time
+    pub normalized: chrono::Duration,
+}
+
+impl Default for WorkingHours {
+    fn default() -> Self {
+        Self {
+            raw: chrono::Duration::zero(),
+            normalized: chrono::Duration::zero(),
+        }
+    }
+}
+
+impl WorkingHours {
+    fn from_range(since: DateTime, till: DateTime, calendar: &impl WorkCalendar) -> Self {
+        if till <= since {
+            return Self::default();
+        }
+        Self {
+            raw: chrono::Duration::seconds((till - since).num_seconds()),
+            normalized: count_work_houres(since, till, calendar),
+        }
+    }
+
+    /// Time outside working days and working time
+    pub fn clipped(&self) -> chrono::Duration {
+        self.raw - self.normalized
+    }
+}
+
+impl std::ops::Add for WorkingHours {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            raw: self.raw + other.raw,
+            normalized: self.normalized + other.normalized,
+        }
+    }
+}
+
+impl std::ops::AddAssign for WorkingHours {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl std::iter::Sum for WorkingHours {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |a, b| a + b)
+    }
+}
+
+/// Tracked time range, open ends are bounded by the report period.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct TimeRange {
+    /// Start of the range, None if the start record is missing.
     pub start: Option<DateTime>,
+    /// End of the range, None if the range is not stopped.
     pub end: Option<DateTime>,
 }
 
+fn work_day() -> chrono::Duration {
+    chrono::Duration::hours(8)
+}
+
 fn day_start() -> chrono::NaiveTime {
     chrono::NaiveTime::from_hms(8, 0, 0)
 }
 fn day_end() -> chrono::NaiveTime {
     chrono::NaiveTime::from_hms(17, 0, 0)
 }
+fn lunch_start() -> chrono::NaiveTime {
+    chrono::NaiveTime::from_hms(12, 0, 0)
+}
+fn lunch_end() -> chrono::NaiveTime {
+    chrono::NaiveTime::from_hms(13, 0, 0)
+}
+
+/// Working time of the date before lunch.
+pub fn morning(date: NaiveDate) -> (DateTime, DateTime) {
+    (
+        DateTime::from_utc(date.and_time(day_start()), chrono::Utc),
+        DateTime::from_utc(date.and_time(lunch_start()), chrono::Utc),
+    )
+}
 
-fn count_hours(since: chrono::NaiveTime, till: chrono::NaiveTime) -> f64 {
+/// Working time of the date after lunch.
+pub fn afternoon(date: NaiveDate) -> (DateTime, DateTime) {
+    (
+        DateTime::from_utc(date.and_time(lunch_end()), chrono::Utc),
+        DateTime::from_utc(date.and_time(day_end()), chrono::Utc),
+    )
+}
+
+fn count_hours(since: chrono::NaiveTime, till: chrono::NaiveTime) -> chrono::Duration {
     let dur = till.max(day_start()).min(day_end()) - since.max(day_start()).min(day_end());
-    let h = dur.num_hours();
-    let fixed_h = if h >= 9 { 8 } else { h };
-    let dur = dur - chrono::Duration::hours(h);
-    let m = dur.num_minutes() as f64 / 60.0;
-    let dur = dur - chrono::Duration::minutes(dur.num_minutes());
-    let s = dur.num_seconds() as f64 / (60.0 * 60.0);
-    let result = fixed_h as f64 + m + s;
-    return result;
+    let dur = chrono::Duration::seconds(dur.num_seconds());
+    if dur.num_hours() >= 9 {
+        work_day()
+    } else {
+        dur
+    }
 }
 
+/// Counts working time between `since` and `till`. Time of a day is limited
+/// by time expected to be worked this day.
 pub fn count_work_houres(
     since: DateTime,
     till: DateTime,
-    calendar: &impl HolidayCalendar<DateTime>,
-) -> f64 {
-    let result = if since.date() == till.date() {
-        if !calendar.is_bday(since) {
-            0.0
-        } else {
-            count_hours(since.time(), till.time())
-        }
-    } else {
-        let days = calendar.bdays(since, till) as f64;
-        let hours = days * 8.0;
-        let left = if calendar.is_bday(since) {
-            count_hours(day_start(), since.time())
+    calendar: &impl WorkCalendar,
+) -> chrono::Duration {
+    if till <= since {
+        return chrono::Duration::zero();
+    }
+    let mut result = chrono::Duration::zero();
+    let mut day = since.date_naive();
+    while day <= till.date_naive() {
+        let s = if day == since.date_naive() {
+            since.time()
         } else {
-            0.0
+            chrono::NaiveTime::from_hms(0, 0, 0)
         };
-        let right = if calendar.is_bday(till) {
-            count_hours(day_start(), till.time())
+        let e = if day == till.date_naive() {
+            till.time()
         } else {
-            0.0
+            chrono::NaiveTime::from_hms(23, 59, 59)
         };
-        hours - left + right
-    };
-
+        result = result + count_hours(s, e).min(expected_work_time(day, calendar));
+        day = day.succ();
+    }
     result
 }
 
-fn end_of_month(d: DateTime) -> DateTime {
+fn start_of_next_month(d: DateTime) -> DateTime {
     let date = NaiveDate::from_ymd(d.year(), d.month(), 1).and_hms(0, 0, 0);
     let date = DateTime::from_utc(date, chrono::Utc);
-    let date = chronoutil::shift_months(date, 1);
-    date - chrono::Duration::seconds(1)
+    chronoutil::shift_months(date, 1)
 }
 
+/// Months from `since` till `till`.
 pub fn month_range(since: DateTime, till: DateTime) -> Vec<chrono::Month> {
     let mut since = NaiveDate::from_ymd(since.year(), since.month(), 1);
     let till = NaiveDate::from_ymd(till.year(), till.month(), 1);
 
     let mut result = Vec::new();
 
-    while since != till {
+    while since <= till {
         result.push(chrono::Month::from_u32(since.month()).unwrap());
         since = chronoutil::shift_months(since, 1);
     }
@@ -162,77 +288,100 @@ impl TimeRange {
         &self,
         global_start: DateTime,
         global_end: DateTime,
-        calendar: &impl HolidayCalendar<DateTime>,
-    ) -> f64 {
+        calendar: &impl WorkCalendar,
+    ) -> WorkingHours {
         let since = self.start.unwrap_or(global_start).max(global_start);
         let till = self.end.unwrap_or(global_end).min(global_end);
-        count_work_houres(since, till, calendar)
+        WorkingHours::from_range(since, till, calendar)
     }
 
     fn month_hours(
         &self,
         global_start: DateTime,
         global_end: DateTime,
-        calendar: &impl HolidayCalendar<DateTime>,
-    ) -> HashMap<chrono::Month, f64> {
+        calendar: &impl WorkCalendar,
+    ) -> HashMap<chrono::Month, WorkingHours> {
         let since = self.start.unwrap_or(global_start).max(global_start);
         let till = self.end.unwrap_or(global_end).min(global_end);
 
         let mut result = HashMap::new();
 
-        let mut m_since = since;
+        let mut s = since;
         loop {
-            let s = m_since;
-            let mut e = end_of_month(m_since);
-            let mut stop = false;
-            if e > till {
-                e = till;
-                stop = true
-            }
-
-            let wh = count_work_houres(s, e, calendar);
-
+            let e = start_of_next_month(s).min(till).max(s);
+            let wh = WorkingHours::from_range(s, e, calendar);
             result.insert(chrono::Month::from_u32(s.month()).unwrap(), wh);
-
-            if stop {
+            if e >= till {
                 break;
-            } else {
-                m_since = e + chrono::Duration::seconds(1);
             }
+            s = e;
         }
 
         result
     }
 }
 
+/// Joins overlapping and adjacent ranges, ranges separated by less than a second
+/// are adjacent. Open range absorbs every range started after it. Ranges without
+/// start are kept as is.
+pub fn merge_ranges(ranges: Vec<TimeRange>) -> Vec<TimeRange> {
+    let (mut result, mut ranges): (Vec<TimeRange>, Vec<TimeRange>) =
+        ranges.into_iter().partition(|r| r.start.is_none());
+    ranges.sort_by_key(|r| r.start);
+
+    let mut merged: Vec<TimeRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        if let Some(last) = merged.last_mut() {
+            match last.end {
+                None => continue,
+                Some(end) if range.start.unwrap() <= end + chrono::Duration::seconds(1) => {
+                    last.end = range.end.map(|e| e.max(end));
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        merged.push(range);
+    }
+
+    result.extend(merged);
+    result
+}
+
+/// Sums working hours of ranges inside the period, which ends now at the latest.
 pub fn working_houres_from_ranges(
     ranges: &[TimeRange],
     global_start: Option<DateTime>,
     global_end: Option<DateTime>,
-    calendar: &impl HolidayCalendar<DateTime>,
-) -> f64 {
+    calendar: &impl WorkCalendar,
+    clock: &dyn Clock,
+) -> WorkingHours {
     let global_start = global_start.unwrap_or(chrono::DateTime::from_utc(
         chrono::NaiveDate::from_ymd(2022, 1, 1).and_hms(0, 0, 0),
         chrono::Utc,
     ));
-    let global_end = global_end.unwrap_or(now()).min(now());
+    let now = clock.now();
+    let global_end = global_end.unwrap_or(now).min(now);
     ranges
         .iter()
         .map(|r| r.work_hours(global_start, global_end, calendar))
         .sum()
 }
 
+/// Working hours of ranges per month inside the period, which ends now at the latest.
 pub fn month_hours(
     ranges: &[TimeRange],
     global_start: Option<DateTime>,
     global_end: Option<DateTime>,
-    calendar: &impl HolidayCalendar<DateTime>,
-) -> HashMap<chrono::Month, f64> {
+    calendar: &impl WorkCalendar,
+    clock: &dyn Clock,
+) -> HashMap<chrono::Month, WorkingHours> {
     let global_start = global_start.unwrap_or(chrono::DateTime::from_utc(
         chrono::NaiveDate::from_ymd(2022, 1, 1).and_hms(0, 0, 0),
         chrono::Utc,
     ));
-    let global_end = global_end.unwrap_or(now()).min(now());
+    let now = clock.now();
+    let global_end = global_end.unwrap_or(now).min(now);
 
     let mut result = HashMap::new();
 
@@ -251,18 +400,238 @@ pub fn month_hours(
     result
 }
 
-pub fn now() -> DateTime {
-    chrono::Utc::now()
+/// Direction tracked time is rounded to an increment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundingMode {
+    /// Round to the next increment.
+    Up,
+    /// Round to the previous increment.
+    Down,
+    /// Round to the closest increment, halves up.
+    Nearest,
+}
+
+impl RoundingMode {
+    /// All modes.
+    pub const ALL: [RoundingMode; 3] =
+        [RoundingMode::Up, RoundingMode::Down, RoundingMode::Nearest];
+
+    /// Value used in settings.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoundingMode::Up => "up",
+            RoundingMode::Down => "down",
+            RoundingMode::Nearest => "nearest",
+        }
+    }
+}
+
+/// Unit of tracked time which is rounded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundingScope {
+    /// Every time range.
+    Range,
+    /// Time tracked per day.
+    Day,
+    /// Time tracked by the task in the report period.
+    Task,
+}
+
+impl RoundingScope {
+    /// All scopes.
+    pub const ALL: [RoundingScope; 3] = [
+        RoundingScope::Range,
+        RoundingScope::Day,
+        RoundingScope::Task,
+    ];
+
+    /// Value used in settings.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoundingScope::Range => "range",
+            RoundingScope::Day => "day",
+            RoundingScope::Task => "task",
+        }
+    }
+}
+
+/// Rule to round tracked time to increments, e.g. quarters of an hour for billing.
+/// Only totals of tasks are rounded, so month hours may not add up to them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rounding {
+    /// Length of the increment in minutes.
+    pub increment_minutes: u32,
+    /// Direction of rounding.
+    pub mode: RoundingMode,
+    /// Time rounded at once.
+    pub scope: RoundingScope,
+}
+
+impl Rounding {
+    /// Rounds the duration to the increment.
+    pub fn round(&self, duration: chrono::Duration) -> chrono::Duration {
+        let increment = i64::from(self.increment_minutes) * 60;
+        if increment == 0 {
+            return duration;
+        }
+        let seconds = duration.num_seconds();
+        let increments = match self.mode {
+            RoundingMode::Up => (seconds + increment - 1).div_euclid(increment),
+            RoundingMode::Down => seconds.div_euclid(increment),
+            RoundingMode::Nearest => (seconds + increment / 2).div_euclid(increment),
+        };
+        chrono::Duration::seconds(increments * increment)
+    }
+
+    fn round_hours(&self, hours: WorkingHours) -> WorkingHours {
+        WorkingHours {
+            raw: self.round(hours.raw),
+            normalized: self.round(hours.normalized),
+        }
+    }
 }
 
-pub fn now_timestamp() -> i64 {
-    to_timestamp(&now())
+/// Working hours of the range per day inside the period.
+fn day_hours(
+    range: &TimeRange,
+    global_start: DateTime,
+    global_end: DateTime,
+    calendar: &impl WorkCalendar,
+) -> BTreeMap<NaiveDate, WorkingHours> {
+    let mut result = BTreeMap::new();
+    let mut since = range.start.unwrap_or(global_start).max(global_start);
+    let till = range.end.unwrap_or(global_end).min(global_end);
+    while since < till {
+        let next_day = DateTime::from_utc(since.date_naive().succ().and_hms(0, 0, 0), chrono::Utc);
+        let e = next_day.min(till);
+        result.insert(
+            since.date_naive(),
+            WorkingHours::from_range(since, e, calendar),
+        );
+        since = e;
+    }
+    result
 }
 
+/// Sums working hours of ranges inside the period, which ends now at the latest.
+/// Hours of every range, day or the whole sum are rounded by the rule.
+pub fn rounded_hours_from_ranges(
+    ranges: &[TimeRange],
+    global_start: DateTime,
+    global_end: DateTime,
+    calendar: &impl WorkCalendar,
+    clock: &dyn Clock,
+    rounding: &Rounding,
+) -> WorkingHours {
+    let global_end = global_end.min(clock.now());
+    let range_days = ranges
+        .iter()
+        .map(|r| day_hours(r, global_start, global_end, calendar));
+    match rounding.scope {
+        RoundingScope::Range => range_days
+            .map(|days| rounding.round_hours(days.into_values().sum()))
+            .sum(),
+        RoundingScope::Day => {
+            let mut days: BTreeMap<NaiveDate, WorkingHours> = BTreeMap::new();
+            for (day, hours) in range_days.flatten() {
+                *days.entry(day).or_default() += hours;
+            }
+            days.into_values().map(|h| rounding.round_hours(h)).sum()
+        }
+        RoundingScope::Task => {
+            rounding.round_hours(range_days.flat_map(|days| days.into_values()).sum())
+        }
+    }
+}
+
+/// Returns wall-clock time tracked per day, ranges are not clipped by working hours.
+/// Parts of ranges are truncated to whole seconds like in [`WorkingHours`].
+pub fn daily_durations(
+    ranges: &[TimeRange],
+    global_start: DateTime,
+    global_end: DateTime,
+) -> BTreeMap<NaiveDate, chrono::Duration> {
+    let mut result = BTreeMap::new();
+    for r in ranges {
+        let mut since = r.start.unwrap_or(global_start).max(global_start);
+        let till = r.end.unwrap_or(global_end).min(global_end);
+        while since < till {
+            let next_day =
+                DateTime::from_utc(since.date_naive().succ().and_hms(0, 0, 0), chrono::Utc);
+            let e = next_day.min(till);
+            let tracked = result
+                .entry(since.date_naive())
+                .or_insert_with(chrono::Duration::zero);
+            *tracked = *tracked + chrono::Duration::seconds((e - since).num_seconds());
+            since = e;
+        }
+    }
+    result
+}
+
+/// Time expected to be worked on the date, absences are subtracted.
+pub fn expected_work_time(date: NaiveDate, calendar: &impl WorkCalendar) -> chrono::Duration {
+    let dt = DateTime::from_utc(date.and_time(day_start()), chrono::Utc);
+    if calendar.is_bday(dt) {
+        (work_day() - calendar.absence_time(date)).max(chrono::Duration::zero())
+    } else {
+        chrono::Duration::zero()
+    }
+}
+
+/// Counts absence days between dates inclusively. Only working days of public
+/// holidays calendar are counted, half day absence is counted as half of a day.
+pub fn absence_days(
+    vacations: Vec<(DateTime, DateTime)>,
+    since: NaiveDate,
+    till: NaiveDate,
+) -> f64 {
+    let holidays = CalendarCombination::holydays_and_vacations(Vec::new());
+    let vacations = VacationsCalendar { vacations };
+    let mut result = chrono::Duration::zero();
+    let mut day = since;
+    while day <= till {
+        result = result + expected_work_time(day, &holidays).min(vacations.absence_time(day));
+        day = day.succ();
+    }
+    result.num_seconds() as f64 / work_day().num_seconds() as f64
+}
+
+/// Source of the current time.
+pub trait Clock {
+    /// Current time.
+    fn now(&self) -> DateTime;
+
+    /// Current time as database timestamp.
+    fn now_timestamp(&self) -> i64 {
+        to_timestamp(&self.now())
+    }
+}
+
+/// Clock returning system time.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime {
+        chrono::Utc::now()
+    }
+}
+
+/// Clock always returning the same time, for tests and reproducible reports.
+pub struct FixedClock(pub DateTime);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime {
+        self.0
+    }
+}
+
+/// Converts time to database timestamp in nanoseconds.
 pub fn to_timestamp(dt: &DateTime) -> i64 {
     dt.timestamp_nanos()
 }
 
+/// Converts database timestamp in nanoseconds to time.
 pub fn from_timestamp(ts: i64) -> DateTime {
     const M: i64 = 1_000_000_000;
     let secs = ts / M;
@@ -272,39 +641,40 @@ pub fn from_timestamp(ts: i64) -> DateTime {
 
 #[cfg(test)]
 mod tests {
-    use super::count_work_houres;
+    use super::{
+        absence_days, afternoon, count_work_houres, daily_durations, expected_work_time,
+        from_timestamp, merge_ranges, month_hours, morning, rounded_hours_from_ranges,
+        working_houres_from_ranges, CalendarCombination, Clock, FixedClock, Rounding, RoundingMode,
+        RoundingScope, TimeRange,
+    };
     use bdays::HolidayCalendar;
     use chrono::DateTime;
+    use chrono::TimeZone;
     use chrono::Utc;
-    use chrono::{NaiveDateTime, NaiveTime};
 
     #[test]
     fn count_work_houres_test() {
         let calendar = bdays::calendars::WeekendsOnly;
-        let now = Utc::now();
-        let mut now = DateTime::from_utc(
-            NaiveDateTime::new(now.date_naive(), NaiveTime::from_hms(8, 0, 0)),
-            Utc,
+        let clock = FixedClock(Utc.ymd(2023, 3, 4).and_hms(8, 0, 0));
+        let saturday = clock.now();
+        assert!(!calendar.is_bday(saturday));
+        assert_eq!(
+            count_work_houres(saturday, saturday + chrono::Duration::hours(1), &calendar),
+            chrono::Duration::hours(0)
         );
-        if !calendar.is_bday(now) {
-            assert_eq!(
-                count_work_houres(now, now + chrono::Duration::hours(1), &calendar),
-                0.0
-            );
-            now = now + chrono::Duration::days(2);
-        }
+        let now = saturday + chrono::Duration::days(2);
         assert_eq!(
             count_work_houres(now, now + chrono::Duration::hours(1), &calendar),
-            1.0
+            chrono::Duration::hours(1)
+        );
+        let tomorrow = now + chrono::Duration::days(1);
+        assert_eq!(
+            count_work_houres(now, tomorrow, &calendar),
+            chrono::Duration::hours(8)
         );
-        let mut tomorrow = now + chrono::Duration::days(1);
-        if !calendar.is_bday(tomorrow) {
-            tomorrow = tomorrow + chrono::Duration::days(2);
-        }
-        assert_eq!(count_work_houres(now, tomorrow, &calendar), 8.0);
         assert_eq!(
             count_work_houres(now, tomorrow + chrono::Duration::hours(2), &calendar),
-            10.0
+            chrono::Duration::hours(10)
         );
         assert_eq!(
             count_work_houres(
@@ -312,7 +682,7 @@ mod tests {
                 tomorrow + chrono::Duration::days(1) + chrono::Duration::hours(2),
                 &calendar
             ),
-            18.0
+            chrono::Duration::hours(18)
         );
         assert_eq!(
             count_work_houres(
@@ -320,7 +690,197 @@ mod tests {
                 tomorrow + chrono::Duration::days(2) + chrono::Duration::hours(2),
                 &calendar
             ),
-            26.0
+            chrono::Duration::hours(26)
         );
+        assert_eq!(
+            count_work_houres(saturday, tomorrow + chrono::Duration::days(5), &calendar),
+            chrono::Duration::hours(40)
+        );
+    }
+
+    #[test]
+    fn many_small_ranges_test() {
+        let calendar = bdays::calendars::WeekendsOnly;
+        let clock = FixedClock(Utc.ymd(2023, 4, 30).and_hms(0, 0, 0));
+        // 12 ranges of 20 minutes and a half second on the last day of March and the
+        // first working day of April, starting at fractions of a second.
+        let ranges: Vec<TimeRange> = [Utc.ymd(2023, 3, 31), Utc.ymd(2023, 4, 3)]
+            .iter()
+            .flat_map(|day| {
+                (0..12).map(move |i| {
+                    let start = day.and_hms_milli(9, 0, 0, 400) + chrono::Duration::minutes(25 * i);
+                    TimeRange {
+                        start: Some(start),
+                        end: Some(start + chrono::Duration::milliseconds(20 * 60_000 + 500)),
+                    }
+                })
+            })
+            .collect();
+        let since = Utc.ymd(2023, 3, 1).and_hms(0, 0, 0);
+
+        let months = month_hours(&ranges, Some(since), None, &calendar, &clock);
+        for month in [chrono::Month::March, chrono::Month::April] {
+            assert_eq!(months[&month].raw, chrono::Duration::hours(4));
+            assert_eq!(months[&month].normalized, chrono::Duration::hours(4));
+        }
+        let total = working_houres_from_ranges(&ranges, Some(since), None, &calendar, &clock);
+        assert_eq!(total.raw, chrono::Duration::hours(8));
+        assert_eq!(total.normalized, chrono::Duration::hours(8));
+        let days = daily_durations(&ranges, since, clock.now());
+        assert_eq!(
+            days.values().copied().collect::<Vec<_>>(),
+            vec![chrono::Duration::hours(4), chrono::Duration::hours(4)]
+        );
+    }
+
+    #[test]
+    fn merge_ranges_test() {
+        let h = |h: i64| from_timestamp(h * 3_600_000_000_000);
+        let range = |s: Option<i64>, e: Option<i64>| TimeRange {
+            start: s.map(h),
+            end: e.map(h),
+        };
+        let merged = merge_ranges(vec![
+            range(Some(5), Some(7)),
+            range(Some(1), Some(3)),
+            range(Some(2), Some(4)),
+            range(Some(4), Some(5)),
+            range(Some(10), Some(11)),
+            range(None, Some(0)),
+            range(Some(12), None),
+            range(Some(13), Some(14)),
+        ]);
+        assert_eq!(
+            merged,
+            vec![
+                range(None, Some(0)),
+                range(Some(1), Some(7)),
+                range(Some(10), Some(11)),
+                range(Some(12), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn rounded_hours_test() {
+        let calendar = bdays::calendars::WeekendsOnly;
+        let clock = FixedClock(Utc.ymd(2023, 3, 10).and_hms(0, 0, 0));
+        let dt = |d, h, m| Utc.ymd(2023, 3, d).and_hms(h, m, 0);
+        let range = |s, e| TimeRange {
+            start: Some(s),
+            end: Some(e),
+        };
+        let ranges = [
+            range(dt(6, 9, 0), dt(6, 9, 10)),
+            range(dt(6, 10, 0), dt(6, 10, 20)),
+            range(dt(7, 9, 0), dt(7, 9, 5)),
+        ];
+        let rounded = |mode, scope| {
+            let rounding = Rounding {
+                increment_minutes: 15,
+                mode,
+                scope,
+            };
+            rounded_hours_from_ranges(
+                &ranges,
+                dt(1, 0, 0),
+                dt(31, 0, 0),
+                &calendar,
+                &clock,
+                &rounding,
+            )
+            .raw
+        };
+        let minutes = chrono::Duration::minutes;
+        assert_eq!(rounded(RoundingMode::Up, RoundingScope::Range), minutes(60));
+        assert_eq!(
+            rounded(RoundingMode::Nearest, RoundingScope::Range),
+            minutes(30)
+        );
+        assert_eq!(rounded(RoundingMode::Up, RoundingScope::Day), minutes(45));
+        assert_eq!(rounded(RoundingMode::Down, RoundingScope::Day), minutes(30));
+        assert_eq!(rounded(RoundingMode::Up, RoundingScope::Task), minutes(45));
+        assert_eq!(
+            rounded(RoundingMode::Nearest, RoundingScope::Task),
+            minutes(30)
+        );
+
+        let rounding = Rounding {
+            increment_minutes: 15,
+            mode: RoundingMode::Up,
+            scope: RoundingScope::Range,
+        };
+        assert_eq!(rounding.round(minutes(15)), minutes(15));
+        assert_eq!(
+            rounding.round(minutes(15) + chrono::Duration::seconds(1)),
+            minutes(30)
+        );
+    }
+
+    #[test]
+    fn daily_durations_test() {
+        let h = |h: i64| from_timestamp(h * 3_600_000_000_000);
+        let ranges = [
+            TimeRange {
+                start: Some(h(20)),
+                end: Some(h(26)),
+            },
+            TimeRange {
+                start: Some(h(30)),
+                end: None,
+            },
+        ];
+        let durations = daily_durations(&ranges, h(21), h(32));
+        let days: Vec<(String, i64)> = durations
+            .iter()
+            .map(|(d, dur)| (d.format("%d.%m.%Y").to_string(), dur.num_hours()))
+            .collect();
+        assert_eq!(
+            days,
+            vec![("01.01.1970".to_owned(), 3), ("02.01.1970".to_owned(), 4)]
+        );
+    }
+
+    #[test]
+    fn partial_absence_test() {
+        // Wednesday
+        let day = chrono::NaiveDate::from_ymd(2026, 10, 14);
+        let at = |h: u32| DateTime::from_utc(day.and_hms(h, 0, 0), Utc);
+
+        let calendar = CalendarCombination::holydays_and_vacations(vec![morning(day)]);
+        assert!(calendar.is_bday(at(8)));
+        assert_eq!(
+            expected_work_time(day, &calendar),
+            chrono::Duration::hours(4)
+        );
+        assert_eq!(
+            count_work_houres(at(8), at(17), &calendar),
+            chrono::Duration::hours(4)
+        );
+
+        let calendar =
+            CalendarCombination::holydays_and_vacations(vec![morning(day), afternoon(day)]);
+        assert!(!calendar.is_bday(at(8)));
+        assert_eq!(
+            expected_work_time(day, &calendar),
+            chrono::Duration::hours(0)
+        );
+
+        let calendar = CalendarCombination::holydays_and_vacations(vec![(at(0), at(23))]);
+        assert!(!calendar.is_bday(at(8)));
+        assert_eq!(
+            count_work_houres(at(8), at(17), &calendar),
+            chrono::Duration::hours(0)
+        );
+    }
+
+    #[test]
+    fn absence_days_test() {
+        let date = |d: u32| chrono::NaiveDate::from_ymd(2026, 12, d);
+        let at = |d: u32, h: u32| DateTime::from_utc(date(d).and_hms(h, 0, 0), Utc);
+        // Christmas holidays and weekend are not counted
+        let vacations = vec![(at(22, 0), at(28, 23)), morning(date(29))];
+        assert_eq!(absence_days(vacations.clone(), date(1), date(31)), 4.5);
+        assert_eq!(absence_days(vacations, date(23), date(25)), 2.0);
     }
 }